@@ -13,6 +13,7 @@ use bevy_event_set::*;
 use crate::{
     MousePosition,
     sim::orders::*,
+    sim::world::data::{ids::BuildingId, model_path},
 };
 use std::{
     collections::HashMap,
@@ -37,6 +38,8 @@ impl Plugin for System {
 pub struct InteractionContext {
     pub order_on_cursor: Option<i32>,
     pub build_mode: bool,
+    // what clicking the ground in build mode places
+    pub building: Option<BuildingId>,
     pub selection: Option<Vec<i32>>,
     pub rotation: u8,
 
@@ -45,7 +48,10 @@ pub struct InteractionContext {
     pub is_shift: bool,
     pub is_alt: bool,
 
+    // the colonist the inventory window is about
+    pub selected: Option<Entity>,
     pub show_inventory: bool,
+    pub inventory_selection: Option<usize>,
 }
 
 
@@ -62,7 +68,6 @@ fn capture_mouse_position(mut mp: ResMut<InteractionContext>, mut events: EventR
 
 fn capture_keyboard_events(
     mut ctx: ResMut<EguiContext>,
-    mut orders: Orders,
     mut ic: ResMut<InteractionContext>,
     mut kb_events: EventReader<KeyboardInput>) {
     // don't handle keyboard input while UI is using
@@ -97,12 +102,6 @@ fn capture_keyboard_events(
                     KeyCode::B => {
                         if event.state == ElementState::Pressed {
                             ic.build_mode = !ic.build_mode;
-                            orders.send(BuildOrder::new(Build{
-                                origin: (0.0, 0.0),
-                                building_id: 0,
-                                rotation: 0,
-                                ghost: false
-                            }));
                         }
                     },
                     KeyCode::Tab => {
@@ -128,6 +127,7 @@ fn capture_mouse_clicks(
     ass: Res<AssetServer>,
     windows: Res<Windows>,
     mut ic: ResMut<InteractionContext>,
+    mut orders: Orders,
     mut orth: Query<(&OrthographicProjection, &Transform)>,
     mut mouse_input_events: EventReader<MouseButtonInput>,
 ) {
@@ -171,13 +171,22 @@ fn capture_mouse_clicks(
                 let p = q.translation + t*rw;
                 info!("{:?}", p);
 
+                if let (true, Some(b)) = (ic.build_mode, ic.building) {
+                    orders.send(BuildOrder::new(Build{
+                        origin: (p.x, p.z),
+                        building: Some(b),
+                        rotation: ic.rotation,
+                        ghost: false
+                    }));
+                    return;
+                }
                 commands.spawn_bundle(
                     (
                         Transform::from_translation(p),
                         GlobalTransform::identity(),
                     )
                 ).with_children(|c|{
-                    c.spawn_scene(ass.load("sphere.gltf#Scene0"));
+                    c.spawn_scene(ass.load(model_path("sphere.gltf#Scene0").as_str()));
                 });
 
                 info!("hit!");
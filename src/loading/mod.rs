@@ -0,0 +1,99 @@
+use bevy::{
+    asset::{HandleId, LoadState},
+    prelude::*,
+    utils::StableHashSet,
+};
+use crate::{
+    sim::world::data::{assets::DataFiles, EntityData, ManifestReport},
+    AppState,
+};
+
+/// System tracks the assets the data files point at until they've all loaded
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_state(LoadingState::Loading)
+            .init_resource::<LoadingProgress>()
+            .add_system_set(SystemSet::on_update(LoadingState::Loading).with_system(track_loading));
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum LoadingState {
+    Loading,
+    Done,
+}
+
+/// LoadingProgress is how far loading the assets got. Failed assets count as finished,
+/// the game can start without them.
+#[derive(Debug, Default, Clone)]
+pub struct LoadingProgress {
+    pub total: usize,
+    pub finished: usize,
+    // the first file still loading
+    pub current: Option<String>,
+    pub failed: Vec<String>,
+}
+
+impl LoadingProgress {
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 0.;
+        }
+        self.finished as f32 / self.total as f32
+    }
+}
+
+/// track_loading checks the load state of every model and data file and moves on to
+/// LoadingState::Done once none are left loading. Data that didn't load shows the
+/// errors instead of the menu.
+fn track_loading(
+    server: Res<AssetServer>,
+    models: Res<EntityData>,
+    files: Res<DataFiles>,
+    report: Res<ManifestReport>,
+    mut progress: ResMut<LoadingProgress>,
+    mut state: ResMut<State<LoadingState>>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    let handles = models.handles.iter()
+        .map(|(p, h)| (p.as_str(), h.id))
+        .chain(files.files.iter().map(|(f, h)| (f.path.as_str(), h.id)));
+
+    let mut next = LoadingProgress::default();
+    // a handle listed twice would count double
+    let mut seen: StableHashSet<HandleId> = Default::default();
+    for (path, id) in handles {
+        if !seen.insert(id) {
+            continue;
+        }
+        next.total += 1;
+        match server.get_load_state(id) {
+            LoadState::Loaded => next.finished += 1,
+            LoadState::Failed => {
+                next.finished += 1;
+                next.failed.push(path.to_string());
+            }
+            _ => {
+                if next.current.is_none() {
+                    next.current = Some(path.to_string());
+                }
+            }
+        }
+    }
+    // the models are only known once the data files are in
+    if next.finished < next.total || !files.loaded {
+        *progress = next;
+        return;
+    }
+    for f in next.failed.iter() {
+        warn!("asset failed to load: {}", f);
+    }
+    info!("loaded {} assets", next.total);
+    *progress = next;
+    state.set(LoadingState::Done).unwrap();
+    if !report.is_ok() {
+        app_state.set(AppState::DataError).unwrap();
+    }
+}
@@ -59,20 +59,26 @@ use crate::{
 use bevy_event_set::*;
 use crate::sim::world::entity::{SpawnLiving, SpawnRequest};
 use bevy::utils::{StableHashSet, StableHashMap};
-use bevy::asset::{HandleId, LoadState};
+use bevy::asset::{AssetServerSettings, HandleId, LoadState};
 use bevy::render::camera::{CameraProjection, OrthographicProjection};
 use bevy_mod_raycast::*;
 
 pub mod ui;
 pub mod input;
 pub mod sim;
+pub mod loading;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum AppState {
     MainMenu,
     InGame,
     NewGame,
+    Settings,
     WorldLoad,
+    // waiting on the models before the game starts
+    Loading,
+    // the game data didn't load, the errors are shown instead of the menu
+    DataError,
 }
 
 impl Default for AppState {
@@ -101,19 +107,105 @@ struct FixedUpdateStage;
 
 type MousePosition = Vec2;
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-enum LoadingState {
-    Loading,
-    Done,
+struct MyRaycastSet;
+
+/// list_content prints which content roots added or changed every data entry
+fn list_content(report: &ManifestReport) {
+    for kind in ManifestType::ALL.iter() {
+        for (id, roots) in contributions(kind) {
+            println!("{:?} {}: {}", kind, id, roots.join(", "));
+        }
+    }
+    for e in report.errors.iter() {
+        eprintln!("{}", e);
+    }
 }
 
-struct MyRaycastSet;
+/// validate_data prints every problem in the game data, for the data pipeline. The exit
+/// code is non-zero when anything is wrong.
+fn validate_data(report: &ManifestReport) -> i32 {
+    let mut errs = report.errors.clone();
+    for e in validate::validate() {
+        // building costs are checked while loading too
+        if !errs.contains(&e) {
+            errs.push(e);
+        }
+    }
+    for e in errs.iter() {
+        eprintln!("{}", e);
+    }
+    if errs.is_empty() {
+        println!("game data is valid");
+        return 0;
+    }
+    eprintln!("{} problem(s) found", errs.len());
+    1
+}
+
+/// export_schemas writes the data file schemas to the directory after the flag, or to
+/// schemas/ when it's missing
+fn export_schemas(args: &[String]) -> i32 {
+    let dir = args.iter()
+        .skip_while(|a| *a != "--export-schemas")
+        .nth(1)
+        .filter(|a| !a.starts_with("--"))
+        .map(|a| a.as_str())
+        .unwrap_or("schemas");
+    match schema::export_schemas(std::path::Path::new(dir)) {
+        Ok(files) => {
+            files.iter().for_each(|f| println!("{}", f));
+            0
+        }
+        Err(e) => {
+            eprintln!("can't write schemas to '{}': {}", dir, e);
+            1
+        }
+    }
+}
+
+/// convert_to_ron writes a RON copy of every YAML data file given after the flag
+fn convert_to_ron(args: &[String]) -> i32 {
+    let paths: Vec<&String> = args.iter()
+        .skip_while(|a| *a != "--convert-to-ron")
+        .skip(1)
+        .take_while(|a| !a.starts_with("--"))
+        .collect();
+    if paths.is_empty() {
+        eprintln!("usage: --convert-to-ron <data file>...");
+        return 1;
+    }
+    let mut failed = 0;
+    for p in paths {
+        match format::convert_to_ron(p) {
+            Ok(to) => println!("{} -> {}", p, to),
+            Err(e) => {
+                eprintln!("{}", e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 { 1 } else { 0 }
+}
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    // schemas come from the types, the data doesn't need to load for them
+    if args.iter().any(|a| a == "--export-schemas") {
+        std::process::exit(export_schemas(&args));
+    }
+    if args.iter().any(|a| a == "--convert-to-ron") {
+        std::process::exit(convert_to_ron(&args));
+    }
+    if args.iter().any(|a| a == "--list-content") {
+        list_content(&ManifestReport::collect());
+        return;
+    }
+    if args.iter().any(|a| a == "--validate-data") {
+        std::process::exit(validate_data(&ManifestReport::collect()));
+    }
+    // the game loads the data files as assets, see data::assets
+    locale::load();
     let mut app = App::new();
-    // AssetLoader::new(LoadingState::Loading, LoadingState::Done)
-    //     // .with_collection::<AudioAssets>()
-    //     .build(&mut app);
     app.insert_resource(WindowDescriptor {
         width: 1280.,
         height: 720.,
@@ -131,9 +223,12 @@ fn main() {
                 ..Default::default()
             })
         .insert_resource(Msaa { samples: 4 })
+        // the data files are next to assets/, the models are looked up in there, see MODEL_DIR
+        .insert_resource(AssetServerSettings {
+            asset_folder: ".".to_string(),
+        })
         .add_plugins(DefaultPlugins)
         .add_plugin(EguiPlugin)
-        .add_state(LoadingState::Loading)
         .add_state(AppState::MainMenu)
         .add_state(GameState::Paused)
         .add_startup_system(setup)
@@ -149,6 +244,7 @@ fn main() {
             CoreStage::PreUpdate,
             update_raycast_with_cursor.before(RaycastSystem::BuildRays),
         )
+        .add_plugin(loading::System)
         .add_plugin(sim::System)
         .add_plugin(input::System)
         // .add_system_set(SystemSet::on_update(AppState::InGame).with_system(game_running.system()))
@@ -201,7 +297,7 @@ fn queue_checker(
     tc.0 += 1;
     send_order::<BuildOrder, Build>(&mut w, Build{
         origin: (0.0, 0.0),
-        building_id: 0,
+        building: None,
         rotation: 0,
         ghost: false
     });
@@ -222,14 +318,10 @@ fn send_order<T: 'static, D>(mut w: &mut World, data: D)
 
 // called to setup the game struct
 fn setup(mut commands: Commands,
-         asset_server: Res<AssetServer>,
          mut sched: ResMut<Schedule>,
          mut meshes: ResMut<Assets<Mesh>>,
          mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    // load all the entity data resources
-    commands.insert_resource(EntityData::new(&asset_server));
-
     // spawn the camera
     // commands.spawn_bundle(PerspectiveCameraBundle {
     //     transform: Transform::from_xyz(40., 15., 1.0).looking_at(Vec3::new(0.0, 0.0, 0.0), Vec3::Y),
@@ -308,7 +400,10 @@ fn inner_start(mut commands: Commands) {
         birthday: None,
         traits: None,
         tags: None,
-        living: None
+        living: None,
+        class: None,
+        core: None,
+        inventory: None
     });
     commands.spawn_living(SpawnRequest{
         id: s!("birch_tree"),
@@ -318,7 +413,10 @@ fn inner_start(mut commands: Commands) {
         birthday: None,
         traits: None,
         tags: None,
-        living: None
+        living: None,
+        class: None,
+        core: None,
+        inventory: None
     });
 }
 
@@ -348,8 +446,12 @@ pub fn render(
                 (*t).translation = l.position;
             },
             None => {
-                let hid = ed.data.get(l.id.as_str());
-                match ass.get_load_state(ass.get_handle::<Scene, HandleId>(hid.unwrap().clone())) {
+                // unknown ones and models that failed to load are listed by the loading screen
+                let hid = match ids::LivingId::of(&l.id).and_then(|id| ed.living.get(&id)) {
+                    Some(h) => h,
+                    None => continue,
+                };
+                match ass.get_load_state(ass.get_handle::<Scene, HandleId>(hid.clone())) {
                     LoadState::Loaded => {
                         info!("my pos {:?}", l.position);
                         commands.spawn_bundle(
@@ -360,7 +462,7 @@ pub fn render(
                                     RayCastMesh::<MyRaycastSet>::default()
                                 )
                             ).with_children(|b| {
-                                b.spawn_scene(ass.load(Entities.get(l.id.as_str()).unwrap().scene().as_str()));
+                                b.spawn_scene(ass.load(model_path(&Entities.get(l.id.as_str()).unwrap().scene()).as_str()));
                             });
                     },
                     _ => {
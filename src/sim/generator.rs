@@ -1,3 +1,5 @@
+use rand::{rngs::StdRng, SeedableRng};
+
 /// WorldOptions are the options set to be used during generation.
 #[derive(Debug, Clone)]
 pub struct WorldOptions {
@@ -58,6 +60,32 @@ impl Default for WorldOptions {
     }
 }
 
+impl WorldOptions {
+    /// seed_value hashes the seed string, FNV-1a so the same seed gives the same world
+    /// everywhere
+    pub fn seed_value(&self) -> u64 {
+        self.seed.bytes().fold(0xcbf29ce484222325, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+}
+
+/// SimRng is the seeded random number generator of the sim. Anything random that should
+/// be the same for the same seed has to roll with this.
+pub struct SimRng(pub StdRng);
+
+impl SimRng {
+    pub fn new(opts: &WorldOptions) -> Self {
+        SimRng(StdRng::seed_from_u64(opts.seed_value()))
+    }
+}
+
+impl Default for SimRng {
+    fn default() -> Self {
+        Self::new(&WorldOptions::default())
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct StoryOptions {
     randomness: f64,
@@ -0,0 +1,250 @@
+use bevy::prelude::*;
+use crate::sim::world::{
+    class::Progression,
+    data::ids::ItemId,
+    entity::{CoreAttributes, Living},
+    equipment::Equipment,
+    faction::Faction,
+};
+
+/// System registers the job queue. Anything that wants work done pushes a Job into
+/// the queue, and colonists pick them up from there.
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Jobs>()
+            .add_system(release_jobs)
+            .add_system(assign_jobs);
+    }
+}
+
+/// the id of a job, 0 is reserved for "no job"
+pub type JobId = i64;
+
+pub const NO_JOB: JobId = 0;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum JobKind {
+    Eat,
+    Sleep,
+    Relax,
+    Build,
+    Haul,
+    Move,
+    Board,
+    Equip,
+    Fabricate,
+    Salvage,
+}
+
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: JobId,
+    pub kind: JobKind,
+    pub priority: u8,
+    pub target: Option<Entity>,
+    pub position: Option<Vec3>,
+    pub assignee: Option<Entity>,
+    // the item the job is about and how many of it
+    pub item: Option<ItemId>,
+    pub count: u32,
+    // the recipe a fabrication job makes
+    pub recipe: Option<String>,
+    // where the items go, for jobs that carry things from the target to somewhere else
+    pub dest: Option<Entity>,
+    // the skill the job needs and the least of it a worker must have
+    pub skill: Option<(String, f32)>,
+    // whoever holds the job's items, nobody else can carry on with it
+    pub holder: Option<Entity>,
+}
+
+impl Job {
+    /// needs jobs are the ones created by the living themselves, they should never be
+    /// handed to someone else
+    pub fn is_need(&self) -> bool {
+        matches!(self.kind, JobKind::Eat | JobKind::Sleep | JobKind::Relax)
+    }
+
+    /// personal jobs are made for one living, needs and the orders given to it. Only
+    /// colony work is handed out by assign_jobs.
+    pub fn is_personal(&self) -> bool {
+        self.is_need() || matches!(self.kind, JobKind::Move | JobKind::Board | JobKind::Equip)
+    }
+
+    /// can_do is true when the attributes are good enough for the job's skill
+    pub fn can_do(&self, attrs: &CoreAttributes) -> bool {
+        match &self.skill {
+            Some((skill, min)) => attrs.skill(skill) >= *min,
+            None => true,
+        }
+    }
+}
+
+/// Jobs captures all created jobs and stores them to be used when ticking
+#[derive(Debug)]
+pub struct Jobs {
+    next_id: JobId,
+    pub list: Vec<Job>,
+}
+
+impl Default for Jobs {
+    fn default() -> Self {
+        Self {
+            next_id: NO_JOB + 1,
+            list: vec![],
+        }
+    }
+}
+
+impl Jobs {
+    /// push adds a new unassigned job to the queue and returns its id
+    pub fn push(&mut self, kind: JobKind, priority: u8, target: Option<Entity>) -> JobId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.list.push(Job {
+            id,
+            kind,
+            priority,
+            target,
+            position: None,
+            assignee: None,
+            item: None,
+            count: 0,
+            recipe: None,
+            dest: None,
+            skill: None,
+            holder: None,
+        });
+        id
+    }
+
+    /// push_at adds a new unassigned job targeting a position rather than an entity
+    pub fn push_at(&mut self, kind: JobKind, priority: u8, position: Vec3) -> JobId {
+        let id = self.push(kind, priority, None);
+        if let Some(j) = self.get_mut(id) {
+            j.position = Some(position);
+        }
+        id
+    }
+
+    /// push_item adds a new unassigned job about some items held by the target
+    pub fn push_item(&mut self, kind: JobKind, priority: u8, target: Entity, item: ItemId, count: u32) -> JobId {
+        let id = self.push(kind, priority, Some(target));
+        if let Some(j) = self.get_mut(id) {
+            j.item = Some(item);
+            j.count = count;
+        }
+        id
+    }
+
+    /// push_haul adds a new unassigned job to carry items from the target to dest
+    pub fn push_haul(&mut self, priority: u8, from: Entity, dest: Entity, item: ItemId, count: u32) -> JobId {
+        let id = self.push_item(JobKind::Haul, priority, from, item, count);
+        if let Some(j) = self.get_mut(id) {
+            j.dest = Some(dest);
+        }
+        id
+    }
+
+    /// push_recipe adds a new unassigned job to make the recipe at the target
+    pub fn push_recipe(&mut self, kind: JobKind, priority: u8, target: Entity, recipe: &str) -> JobId {
+        let id = self.push(kind, priority, Some(target));
+        if let Some(j) = self.get_mut(id) {
+            j.recipe = Some(recipe.to_string());
+            j.count = 1;
+        }
+        id
+    }
+
+    /// require sets the skill a worker needs for the job
+    pub fn require(&mut self, id: JobId, skill: &str, min: f32) {
+        if let Some(j) = self.get_mut(id) {
+            j.skill = Some((skill.to_string(), min));
+        }
+    }
+
+    pub fn get(&self, id: JobId) -> Option<&Job> {
+        self.list.iter().find(|j| j.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: JobId) -> Option<&mut Job> {
+        self.list.iter_mut().find(|j| j.id == id)
+    }
+
+    /// assign hands the job to the given entity
+    pub fn assign(&mut self, id: JobId, e: Entity) {
+        if let Some(j) = self.get_mut(id) {
+            j.assignee = Some(e);
+        }
+    }
+
+    /// interrupt unassigns a job so it goes back into the queue. Personal jobs belong to
+    /// the living they were made for, so those are dropped instead.
+    pub fn interrupt(&mut self, id: JobId) {
+        if let Some(j) = self.get(id) {
+            if j.is_personal() {
+                self.finish(id);
+                return;
+            }
+        }
+        if let Some(j) = self.get_mut(id) {
+            j.assignee = None;
+        }
+    }
+
+    /// finish removes the job from the queue
+    pub fn finish(&mut self, id: JobId) -> Option<Job> {
+        let idx = self.list.iter().position(|j| j.id == id)?;
+        Some(self.list.remove(idx))
+    }
+
+    /// next_for returns the highest priority job of any kind that nobody is working on
+    /// and that the worker can do. Jobs whose items the worker still holds come first.
+    pub fn next_for(&self, e: Entity, attrs: &CoreAttributes) -> Option<&Job> {
+        self.list.iter()
+            .filter(|j| j.assignee.is_none() && !j.is_personal())
+            .filter(|j| j.holder.map(|h| h == e).unwrap_or(true))
+            .filter(|j| j.can_do(attrs))
+            .max_by_key(|j| (j.holder == Some(e), j.priority))
+    }
+}
+
+/// release_jobs puts the jobs of despawned assignees back into the queue, or drops them
+/// if they were personal. Jobs whose items were held by a despawned living are dropped,
+/// the items went with it.
+fn release_jobs(mut jobs: ResMut<Jobs>, alive: Query<Entity>) {
+    let gone = |e: Option<Entity>| e.map(|e| alive.get(e).is_err()).unwrap_or(false);
+    let lost: Vec<JobId> = jobs.list.iter()
+        .filter(|j| gone(j.holder))
+        .map(|j| j.id)
+        .collect();
+    for id in lost {
+        jobs.finish(id);
+    }
+    let orphaned: Vec<JobId> = jobs.list.iter()
+        .filter(|j| gone(j.assignee))
+        .map(|j| j.id)
+        .collect();
+    for id in orphaned {
+        jobs.interrupt(id);
+    }
+}
+
+/// assign_jobs hands every idle colonist the best job it can do. Wildlife, hostiles and
+/// anything else outside the colony never takes colony work.
+fn assign_jobs(
+    mut jobs: ResMut<Jobs>,
+    mut livings: Query<(Entity, &mut Living, &Faction, Option<&Progression>, Option<&Equipment>)>,
+) {
+    for (e, mut l, f, p, eq) in livings.iter_mut() {
+        if *f != Faction::Colony || l.has_job != NO_JOB {
+            continue;
+        }
+        let attrs = l.attributes(p, eq);
+        if let Some(id) = jobs.next_for(e, &attrs).map(|j| j.id) {
+            jobs.assign(id, e);
+            l.has_job = id;
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use bevy::app::App;
 use bevy::ecs::event::Events;
-use bevy::prelude::{Plugin, World};
-use crate::sim::generator::WorldOptions;
+use bevy::prelude::{Plugin, Res, ResMut, World};
+use crate::sim::generator::{SimRng, WorldOptions};
 use crate::sim::world::data::EntityData;
 use crate::sim::orders::*;
 use crate::sim::world::tilemap::{TileChunk, TileLayer};
@@ -9,17 +9,13 @@ use crate::sim::world::tilemap::{TileChunk, TileLayer};
 pub mod world;
 pub mod generator;
 pub mod orders;
+pub mod jobs;
 
 #[derive(Default)]
 pub struct Sim {
     ground: TileLayer<u16>
 }
 
-// Jobs captures all created jobs and stores them to be used when ticking
-// pub struct Jobs {
-//     pub list: Vec<Job>
-// }
-
 impl Sim {
     pub fn new(mut w: &mut World, opts: WorldOptions) -> Self {
         w.insert_resource(opts.clone());
@@ -49,7 +45,34 @@ pub struct System;
 
 impl Plugin for System {
     fn build(&self, app: &mut App) {
-        app.add_plugin(orders::System)
-            .add_plugin(world::entity::System); // does nothing
+        app.init_resource::<SimRng>()
+            .add_system(seed_rng)
+            .add_plugin(world::data::assets::System)
+            .add_plugin(orders::System)
+            .add_plugin(jobs::System)
+            .add_plugin(world::entity::System)
+            .add_plugin(world::needs::System)
+            .add_plugin(world::body::System)
+            .add_plugin(world::mech::System)
+            .add_plugin(world::class::System)
+            .add_plugin(world::faction::System)
+            .add_plugin(world::beauty::System)
+            .add_plugin(world::equipment::System)
+            .add_plugin(world::fabrication::System)
+            .add_plugin(world::salvage::System)
+            .add_plugin(world::buildings::System)
+            .add_plugin(world::hauling::System)
+            .add_plugin(world::ledger::System)
+            .add_plugin(world::loot::System)
+            .add_plugin(world::item::System);
     }
-}
\ No newline at end of file
+}
+/// seed_rng reseeds the sim's rng whenever new world options are set, so a game rolls the
+/// same for the same seed
+fn seed_rng(opts: Option<Res<WorldOptions>>, mut rng: ResMut<SimRng>) {
+    if let Some(opts) = opts {
+        if opts.is_changed() {
+            *rng = SimRng::new(&opts);
+        }
+    }
+}
@@ -2,6 +2,17 @@ use bevy::prelude::*;
 use bevy::ecs::event::Events;
 use bevy_event_set::*;
 use rand::{thread_rng, Rng};
+use crate::sim::{
+    jobs::{JobKind, Jobs},
+    world::{
+        buildings::{Blueprint, SpawnBlueprint},
+        data::ids::{BuildingId, ItemId, MaterialId},
+        entity::Living,
+        ledger::Ledger,
+        mech::{Mech, Piloting},
+        stockpile::{DesignateStockpile, Stockpile, StockpileFilter},
+    },
+};
 
 /// System creates the events that will be used for the order system. These events have
 /// receivers inside the sim itself. The expectation is that something will send these
@@ -12,11 +23,15 @@ impl Plugin for System {
     fn build(&self, app: &mut App) {
         // app.init_resource::<Orders>()
         app.add_event_set::<Orders>()
-            .add_system(build_order_handler);
+            .add_event::<UnaffordableBuild>()
+            .add_system(build_order_handler)
+            .add_system(move_order_handler)
+            .add_system(pilot_order_handler)
+            .add_system(stockpile_order_handler);
     }
 }
 
-event_set!(Orders { BuildOrder, HarvestOrder, MoveOrder, InterfaceOrder });
+event_set!(Orders { BuildOrder, HarvestOrder, MoveOrder, InterfaceOrder, PilotOrder, StockpileOrder });
 
 pub type MoveOrder = InputOrder<Move>;
 #[derive(Debug, Default, Copy, Clone)]
@@ -25,11 +40,31 @@ pub struct Move {
     pub entity: u32
 }
 
-pub type BuildOrder = InputOrder<Build>;
+pub type PilotOrder = InputOrder<Pilot>;
 #[derive(Debug, Default, Copy, Clone)]
+pub struct Pilot {
+    pub pilot: u32,
+    pub mech: u32,
+    // climb out of the mech instead of into it
+    pub disembark: bool,
+}
+
+pub type StockpileOrder = InputOrder<Zone>;
+#[derive(Debug, Default, Clone)]
+pub struct Zone {
+    pub name: String,
+    pub cells: Vec<(i32, i32)>,
+    pub categories: Vec<String>,
+    pub materials: Vec<MaterialId>,
+    pub min_quality: i32,
+}
+
+pub type BuildOrder = InputOrder<Build>;
+#[derive(Debug, Default, Clone)]
 pub struct Build {
     pub origin: (f32, f32),
-    pub building_id: i32,
+    // None until the player picks a building
+    pub building: Option<BuildingId>,
     pub rotation: u8,
     pub ghost: bool,
 }
@@ -81,14 +116,135 @@ impl<T> MakeOrder<T> for InputOrder<T> {
     }
 }
 
-fn build_order_handler(mut evs: EventReader<InputOrder<Build>>) {
+/// UnaffordableBuild is sent for build orders the colony doesn't have the materials for,
+/// no blueprint is placed for them
+#[derive(Debug, Clone)]
+pub struct UnaffordableBuild {
+    pub order: u64,
+    pub building: BuildingId,
+    pub missing: Vec<(ItemId, u32)>,
+}
+
+/// build_order_handler places a blueprint for every affordable build order and reserves
+/// its materials. Ghost orders only check whether the building is affordable.
+fn build_order_handler(
+    mut commands: Commands,
+    mut evs: EventReader<BuildOrder>,
+    mut ledger: ResMut<Ledger>,
+    mut unaffordable: EventWriter<UnaffordableBuild>,
+) {
+    for e in evs.iter() {
+        let d = &e.data;
+        let (id, bd) = match d.building.and_then(|id| Some((id, id.data()?))) {
+            Some(found) => found,
+            None => {
+                debug!("build order without a loaded building: {:?}", d.building);
+                continue;
+            }
+        };
+        let missing = ledger.missing(bd.costs());
+        if !missing.is_empty() {
+            unaffordable.send(UnaffordableBuild {
+                order: e.id,
+                building: id,
+                missing,
+            });
+            continue;
+        }
+        if d.ghost {
+            continue;
+        }
+        for c in bd.costs() {
+            ledger.reserve(c.name, c.value as u32);
+        }
+        let position = Vec3::new(d.origin.0, 0., d.origin.1);
+        commands.spawn_blueprint(Blueprint::new(&bd.id, None), position);
+    }
+}
+
+// move orders replace whatever the living or mech was doing
+const MOVE_PRIORITY: u8 = 9;
+
+fn move_order_handler(
+    mut evs: EventReader<MoveOrder>,
+    mut jobs: ResMut<Jobs>,
+    mut livings: Query<(Entity, &mut Living, Option<&Piloting>)>,
+    mechs: Query<Entity, With<Mech>>,
+) {
+    for e in evs.iter() {
+        let d: Move = e.data;
+        let target = Vec3::new(d.target.0, 0., d.target.1);
+        let mech = match livings.iter_mut().find(|(le, _, _)| le.id() == d.entity) {
+            Some((le, mut l, None)) => {
+                jobs.interrupt(l.has_job);
+                let id = jobs.push_at(JobKind::Move, MOVE_PRIORITY, target);
+                jobs.assign(id, le);
+                l.has_job = id;
+                continue;
+            }
+            // a pilot can't walk off on its own, the mech it's in goes instead
+            Some((_, _, Some(p))) => mechs.get(p.0).ok(),
+            None => mechs.iter().find(|me| me.id() == d.entity),
+        };
+        if let Some(me) = mech {
+            let old: Vec<_> = jobs.list.iter()
+                .filter(|j| j.kind == JobKind::Move && j.assignee == Some(me))
+                .map(|j| j.id)
+                .collect();
+            old.into_iter().for_each(|id| { jobs.finish(id); });
+            let id = jobs.push_at(JobKind::Move, MOVE_PRIORITY, target);
+            jobs.assign(id, me);
+        }
+    }
+}
+
+fn pilot_order_handler(
+    mut commands: Commands,
+    mut evs: EventReader<PilotOrder>,
+    mut jobs: ResMut<Jobs>,
+    mut livings: Query<(Entity, &mut Living, Option<&Piloting>)>,
+    mut mechs: Query<(Entity, &mut Mech)>,
+) {
     for e in evs.iter() {
-        let d: Build = e.data;
-        let position_is_valid = d.origin > (-1., -2.);
-        // if !position_is_valid {
-        //     // play dot noise
-        //     // return;
-        // }
-        info!("{:?}", e);
+        let d: Pilot = e.data;
+        let (le, mut l, piloting) = match livings.iter_mut().find(|(le, _, _)| le.id() == d.pilot) {
+            Some(l) => l,
+            None => continue,
+        };
+        let (me, mut m) = match mechs.iter_mut().find(|(me, _)| me.id() == d.mech) {
+            Some(m) => m,
+            None => continue,
+        };
+        if d.disembark {
+            if m.pilot == Some(le) {
+                m.pilot = None;
+                commands.entity(le).remove::<Piloting>();
+            }
+            continue;
+        }
+        if piloting.is_some() {
+            continue;
+        }
+        jobs.interrupt(l.has_job);
+        let id = jobs.push(JobKind::Board, MOVE_PRIORITY, Some(me));
+        jobs.assign(id, le);
+        l.has_job = id;
+    }
+}
+
+fn stockpile_order_handler(mut commands: Commands, mut evs: EventReader<StockpileOrder>) {
+    for e in evs.iter() {
+        let d = &e.data;
+        if d.cells.is_empty() {
+            continue;
+        }
+        let mut s = Stockpile::new(&d.name, d.cells.iter().map(|c| IVec2::new(c.0, c.1)).collect());
+        s.priority = e.priority;
+        s.filter = StockpileFilter {
+            categories: d.categories.clone(),
+            materials: d.materials.clone(),
+            min_quality: d.min_quality,
+        };
+        commands.designate_stockpile(s);
     }
 }
@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+use crate::sim::world::{
+    buildings::Building,
+    entity::Living,
+    item::ItemStack,
+    needs::{Needs, NEED_MAX},
+};
+
+/// System lets livings react to how nice their surroundings are
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_system(react_to_beauty);
+    }
+}
+
+// how far a living can see things that count towards beauty
+pub const BEAUTY_RADIUS: f32 = 6.;
+// comfort gained per second per point of beauty
+const COMFORT_PER_BEAUTY: f32 = 0.002;
+
+/// beauty_at sums the attractiveness of everything in range, closer things count more
+pub fn beauty_at<I>(pos: Vec3, things: I) -> f32
+    where I: Iterator<Item = (Vec3, i32)> {
+    things
+        .map(|(p, a)| (p.distance(pos), a))
+        .filter(|(d, _)| *d <= BEAUTY_RADIUS)
+        .map(|(d, a)| a as f32 * (1. - d / BEAUTY_RADIUS))
+        .sum()
+}
+
+/// react_to_beauty raises comfort in beautiful places and drains it in ugly ones
+fn react_to_beauty(
+    time: Res<Time>,
+    mut q: Query<(&Living, &mut Needs)>,
+    items: Query<(&Transform, &ItemStack)>,
+    buildings: Query<(&Transform, &Building)>,
+) {
+    let dt = time.delta_seconds();
+    let things: Vec<(Vec3, i32)> = items.iter()
+        .map(|(t, s)| (t.translation, s.item.attractiveness * s.count as i32))
+        .chain(buildings.iter().map(|(t, b)| {
+            (t.translation, b.data().and_then(|d| d.beauty).unwrap_or(0))
+        }))
+        .filter(|(_, a)| *a != 0)
+        .collect();
+    if things.is_empty() {
+        return;
+    }
+    for (l, mut n) in q.iter_mut() {
+        let b = beauty_at(l.position, things.iter().copied());
+        n.comfort = (n.comfort + b * COMFORT_PER_BEAUTY * dt).clamp(0., NEED_MAX);
+    }
+}
@@ -0,0 +1,328 @@
+use bevy::prelude::*;
+use bevy::utils::StableHashMap;
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use crate::sim::world::{
+    class::{ExperienceModifier, Progression},
+    data::ids::LivingId,
+    entity::{Living, Trait},
+    equipment::Equipment,
+    faction::Faction,
+    item::{MechSlot, OrganicSlot},
+};
+
+// the attr names trait modifiers use to change the body
+pub const BODY_ATTRS: [&str; 1] = ["size"];
+// the smallest a body can be scaled to, so no part starts out destroyed
+const MIN_SCALE: f32 = 0.1;
+// the armor that halves the damage, more armor keeps helping less and less
+const ARMOR_HALVES: f32 = 100.;
+
+/// size_factor is how much bigger than usual the traits make a living, from the sum of
+/// their `size` attrs as a percent
+pub fn size_factor(traits: &[Trait]) -> f32 {
+    let size: i32 = traits.iter()
+        .flat_map(|t| t.modifiers.iter())
+        .filter(|m| m.attr.as_deref() == Some("size"))
+        .map(|m| m.value)
+        .sum();
+    1. + size as f32 / 100.
+}
+
+/// System ticks bleeding and healing on every body
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Died>()
+            .add_system(tick_bodies);
+    }
+}
+
+// hp healed per second on an organic part that isn't bleeding
+const HEAL_RATE: f32 = 0.01;
+// bleeding slows by this much per second as wounds clot
+const CLOT_RATE: f32 = 0.02;
+// a functioning part never drops below this efficiency until it's destroyed
+const MIN_EFFICIENCY: f32 = 0.1;
+
+/// BodyPlan is declared by a race and decides which slots its bodies are made of
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum BodyPlan {
+    Organic,
+    Mechanical,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum BodySlot {
+    Organic(OrganicSlot),
+    Mech(MechSlot),
+}
+
+impl BodySlot {
+    pub fn base_hp(self) -> f32 {
+        match self {
+            BodySlot::Organic(s) => match s {
+                OrganicSlot::Head => 25.,
+                OrganicSlot::Chest => 40.,
+                OrganicSlot::Shoulders => 20.,
+                OrganicSlot::Arms => 20.,
+                OrganicSlot::Hands => 10.,
+                OrganicSlot::Legs => 30.,
+                OrganicSlot::Feet => 10.,
+            },
+            BodySlot::Mech(s) => match s {
+                MechSlot::Head => 40.,
+                MechSlot::Processor => 30.,
+                MechSlot::Locomotion => 60.,
+                MechSlot::Arms => 40.,
+                MechSlot::Back => 50.,
+                MechSlot::Shoulders => 40.,
+                MechSlot::Booster => 20.,
+                MechSlot::Drone => 10.,
+            },
+        }
+    }
+
+    /// vital slots kill the body when destroyed
+    pub fn is_vital(self) -> bool {
+        matches!(
+            self,
+            BodySlot::Organic(OrganicSlot::Head)
+                | BodySlot::Organic(OrganicSlot::Chest)
+                | BodySlot::Mech(MechSlot::Processor)
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum InjuryKind {
+    Cut,
+    Bruise,
+    Burn,
+    Fracture,
+    // mechanical damage
+    Dent,
+    Short,
+}
+
+impl InjuryKind {
+    /// bleed is the hp lost per second per point of damage on an organic part
+    fn bleed(self) -> f32 {
+        match self {
+            InjuryKind::Cut => 0.05,
+            InjuryKind::Fracture => 0.02,
+            _ => 0.,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Injury {
+    pub kind: InjuryKind,
+    pub damage: f32,
+    pub bleeding: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct BodyPart {
+    pub slot: BodySlot,
+    pub hp: f32,
+    pub max_hp: f32,
+    pub injuries: Vec<Injury>,
+}
+
+impl BodyPart {
+    pub fn new(slot: BodySlot) -> Self {
+        Self {
+            slot,
+            hp: slot.base_hp(),
+            max_hp: slot.base_hp(),
+            injuries: vec![],
+        }
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.hp <= 0.
+    }
+
+    /// efficiency is how well the part works, from 0 when destroyed to 1 when healthy
+    pub fn efficiency(&self) -> f32 {
+        if self.is_destroyed() {
+            return 0.;
+        }
+        (self.hp / self.max_hp).max(MIN_EFFICIENCY)
+    }
+
+    pub fn bleeding(&self) -> f32 {
+        self.injuries.iter().map(|i| i.bleeding).sum()
+    }
+}
+
+/// Body is the collection of parts making up a living
+#[derive(Debug, Clone)]
+pub struct Body {
+    pub plan: BodyPlan,
+    pub parts: StableHashMap<BodySlot, BodyPart>,
+}
+
+impl Body {
+    pub fn new(plan: BodyPlan) -> Self {
+        let slots: Vec<BodySlot> = match plan {
+            BodyPlan::Organic => vec![
+                OrganicSlot::Head,
+                OrganicSlot::Chest,
+                OrganicSlot::Shoulders,
+                OrganicSlot::Arms,
+                OrganicSlot::Hands,
+                OrganicSlot::Legs,
+                OrganicSlot::Feet,
+            ].into_iter().map(BodySlot::Organic).collect(),
+            BodyPlan::Mechanical => vec![
+                MechSlot::Head,
+                MechSlot::Processor,
+                MechSlot::Locomotion,
+                MechSlot::Arms,
+                MechSlot::Back,
+                MechSlot::Shoulders,
+            ].into_iter().map(BodySlot::Mech).collect(),
+        };
+        Self {
+            plan,
+            parts: slots.into_iter().map(|s| (s, BodyPart::new(s))).collect(),
+        }
+    }
+
+    /// for_living builds the body of a race's plan, sized by any trait with a `size` attr.
+    /// Trait values are a percent change in the hp of every part.
+    pub fn for_living(plan: BodyPlan, traits: &[Trait]) -> Self {
+        let mut b = Self::new(plan);
+        b.scale(size_factor(traits));
+        b
+    }
+
+    /// scale multiplies the hp of every part
+    pub fn scale(&mut self, f: f32) {
+        let f = f.max(MIN_SCALE);
+        for p in self.parts.values_mut() {
+            p.max_hp *= f;
+            p.hp *= f;
+        }
+    }
+
+    /// damage injures the part in the given slot, less the armor worn over it. Mechanical
+    /// bodies don't bleed.
+    pub fn damage(&mut self, slot: BodySlot, kind: InjuryKind, damage: f32, eq: Option<&Equipment>) {
+        let armor = match (slot, eq) {
+            (BodySlot::Organic(s), Some(eq)) => eq.armor_at(s).max(0.),
+            _ => 0.,
+        };
+        let damage = damage * ARMOR_HALVES / (ARMOR_HALVES + armor);
+        let plan = self.plan;
+        if let Some(p) = self.parts.get_mut(&slot) {
+            p.hp = (p.hp - damage).max(0.);
+            let bleeding = match plan {
+                BodyPlan::Organic => kind.bleed() * damage,
+                BodyPlan::Mechanical => 0.,
+            };
+            p.injuries.push(Injury { kind, damage, bleeding });
+        }
+    }
+
+    pub fn efficiency(&self, slot: BodySlot) -> f32 {
+        self.parts.get(&slot).map(|p| p.efficiency()).unwrap_or(0.)
+    }
+
+    /// move_speed is the movement multiplier from the legs or locomotion
+    pub fn move_speed(&self) -> f32 {
+        match self.plan {
+            BodyPlan::Organic => self.efficiency(BodySlot::Organic(OrganicSlot::Legs)),
+            BodyPlan::Mechanical => self.efficiency(BodySlot::Mech(MechSlot::Locomotion)),
+        }
+    }
+
+    /// manipulation is the crafting multiplier from the hands, or the arms on a mech
+    pub fn manipulation(&self) -> f32 {
+        match self.plan {
+            BodyPlan::Organic => {
+                self.efficiency(BodySlot::Organic(OrganicSlot::Hands))
+                    * self.efficiency(BodySlot::Organic(OrganicSlot::Arms)).sqrt()
+            }
+            BodyPlan::Mechanical => self.efficiency(BodySlot::Mech(MechSlot::Arms)),
+        }
+    }
+
+    pub fn bleeding(&self) -> f32 {
+        self.parts.values().map(|p| p.bleeding()).sum()
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.parts.values().any(|p| p.slot.is_vital() && p.is_destroyed())
+    }
+
+    /// cause_of_death is the last injury to a destroyed vital part
+    pub fn cause_of_death(&self) -> Option<InjuryKind> {
+        self.parts.values()
+            .filter(|p| p.slot.is_vital() && p.is_destroyed())
+            .find_map(|p| p.injuries.last())
+            .map(|i| i.kind)
+    }
+
+    /// tick bleeds and heals the body over dt seconds
+    pub fn tick(&mut self, dt: f32) {
+        if self.plan != BodyPlan::Organic {
+            return;
+        }
+        for p in self.parts.values_mut() {
+            let bleeding = p.bleeding();
+            if bleeding > 0. {
+                p.hp = (p.hp - bleeding * dt).max(0.);
+                p.injuries.iter_mut().for_each(|i| {
+                    i.bleeding = (i.bleeding - CLOT_RATE * dt).max(0.);
+                });
+            } else if !p.is_destroyed() {
+                // destroyed parts need a doctor, or a replacement
+                p.hp = (p.hp + HEAL_RATE * dt).min(p.max_hp);
+                if p.hp >= p.max_hp {
+                    p.injuries.clear();
+                }
+            }
+        }
+    }
+}
+
+/// Died is sent when a body dies, just before the entity is despawned
+#[derive(Debug, Clone)]
+pub struct Died {
+    pub entity: Entity,
+    // the living's LivingData
+    pub id: Option<LivingId>,
+    pub position: Vec3,
+    pub cause: Option<InjuryKind>,
+    pub faction: Option<Faction>,
+    pub rank: Option<ExperienceModifier>,
+}
+
+fn tick_bodies(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut died: EventWriter<Died>,
+    mut q: Query<(Entity, &mut Body, Option<&Living>, Option<&Faction>, Option<&Progression>)>,
+) {
+    let dt = time.delta_seconds();
+    for (e, mut b, l, f, p) in q.iter_mut() {
+        b.tick(dt);
+        if b.is_dead() {
+            info!("{:?} has died", e);
+            died.send(Died {
+                entity: e,
+                id: l.and_then(|l| LivingId::of(&l.id)),
+                position: l.map(|l| l.position).unwrap_or_default(),
+                cause: b.cause_of_death(),
+                faction: f.copied(),
+                rank: p.map(|p| p.rank),
+            });
+            commands.entity(e).despawn_recursive();
+        }
+    }
+}
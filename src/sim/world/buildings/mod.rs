@@ -0,0 +1,250 @@
+use bevy::prelude::*;
+use bevy::ecs::system::EntityCommands;
+use std::sync::Arc;
+use crate::sim::{
+    jobs::{JobId, JobKind, Jobs, NO_JOB},
+    world::{
+        body::Body,
+        class::{ExperienceEvent, Progression},
+        data::{assets::DataChanged, ids::ItemId, BuildingData, Buildings, ManifestType, Properties},
+        equipment::Equipment,
+        entity::Living,
+        fabrication::Workstation,
+        item::{Inventory, Storage},
+        needs::Bed,
+        raw::MaterialRoll,
+    },
+};
+
+/// System turns blueprints with all their materials delivered into buildings
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_system(plan_construction)
+            .add_system(work_construction)
+            .add_system(refresh_buildings);
+    }
+}
+
+// how close a builder has to be to the blueprint
+const REACH: f32 = 1.5;
+const BUILD_PRIORITY: u8 = 5;
+pub const BUILD_SKILL: &str = "construction";
+
+/// Building is a constructed building in the world
+#[derive(Debug, Default, Clone)]
+pub struct Building {
+    pub id: String,
+    pub hp: f32,
+    pub max_hp: f32,
+    pub material: Option<MaterialRoll>,
+}
+
+impl Building {
+    /// new builds the building at full hp, the material's durability changes the hp
+    pub fn new(id: &str, material: Option<MaterialRoll>) -> Option<Self> {
+        let bd = Buildings.get(id)?;
+        let hp = Self::max_hp_of(&bd, material.as_ref());
+        Some(Self {
+            id: bd.id.clone(),
+            hp,
+            max_hp: hp,
+            material,
+        })
+    }
+
+    pub fn max_hp_of(bd: &BuildingData, material: Option<&MaterialRoll>) -> f32 {
+        let hp = bd.base_hp as f32;
+        match material {
+            Some(m) => MaterialRoll::apply(hp, m.durability),
+            None => hp,
+        }
+    }
+
+    pub fn data(&self) -> Option<Arc<BuildingData>> {
+        Buildings.get(&self.id)
+    }
+}
+
+/// refresh_buildings updates the hp of standing buildings when their data is reloaded,
+/// damage is kept as a fraction of the max
+fn refresh_buildings(mut evs: EventReader<DataChanged>, mut q: Query<(&mut Building, Option<&mut Bed>)>) {
+    if !evs.iter().any(|e| e.kind == ManifestType::BuildingData) {
+        return;
+    }
+    for (mut b, bed) in q.iter_mut() {
+        let bd = match b.data() {
+            Some(bd) => bd,
+            None => continue,
+        };
+        if let (Some(mut bed), Some(comfort)) = (bed, bd.bed) {
+            bed.comfort = comfort;
+        }
+        let max_hp = Building::max_hp_of(&bd, b.material.as_ref());
+        if max_hp == b.max_hp {
+            continue;
+        }
+        let frac = if b.max_hp > 0. { b.hp / b.max_hp } else { 1. };
+        b.max_hp = max_hp;
+        b.hp = max_hp * frac;
+    }
+}
+
+pub trait SpawnBuilding<'w, 's> {
+    /// spawn_building spawns a finished building. Buildings that store things get an
+    /// inventory, usable ones a workstation and beds a Bed.
+    fn spawn_building<'a>(&'a mut self, b: Building, position: Vec3) -> EntityCommands<'w, 's, 'a>;
+}
+
+impl<'w, 's> SpawnBuilding<'w, 's> for Commands<'w, 's> {
+    fn spawn_building<'a>(&'a mut self, b: Building, position: Vec3) -> EntityCommands<'w, 's, 'a> {
+        let bd = b.data();
+        let storage = bd.as_ref().and_then(|d| d.storage);
+        let usable = bd.as_ref().map(|d| d.has_property(Properties::Usable)).unwrap_or(false);
+        let bed = bd.as_ref().and_then(|d| d.bed);
+        let mut base = self.spawn();
+        base.insert(b)
+            .insert(Transform::from_translation(position))
+            .insert(GlobalTransform::identity());
+        if let Some(s) = storage {
+            base.insert(Inventory::new(s))
+                .insert(Storage);
+        }
+        if usable {
+            base.insert(Workstation::default());
+        }
+        if let Some(comfort) = bed {
+            base.insert(Bed { comfort });
+        }
+        base
+    }
+}
+
+/// Blueprint is a building waiting to be built. Its materials are hauled into its
+/// inventory, once they are all there it can be built.
+#[derive(Debug, Default, Clone)]
+pub struct Blueprint {
+    pub building: String,
+    pub material: Option<MaterialRoll>,
+    pub job: JobId,
+    // seconds of work done, the building is done at its base_time
+    pub progress: f32,
+}
+
+impl Blueprint {
+    pub fn new(building: &str, material: Option<MaterialRoll>) -> Self {
+        Self {
+            building: building.to_string(),
+            material,
+            job: NO_JOB,
+            progress: 0.,
+        }
+    }
+
+    pub fn data(&self) -> Option<Arc<BuildingData>> {
+        Buildings.get(&self.building)
+    }
+
+    /// missing is how many of each cost item still has to be delivered
+    pub fn missing(&self, delivered: &Inventory) -> Vec<(ItemId, u32)> {
+        let bd = match self.data() {
+            Some(bd) => bd,
+            None => return vec![],
+        };
+        bd.costs()
+            .iter()
+            .filter_map(|c| {
+                let left = (c.value.max(0) as u32).saturating_sub(delivered.count(c.name));
+                if left == 0 {
+                    return None;
+                }
+                Some((c.name, left))
+            })
+            .collect()
+    }
+}
+
+pub trait SpawnBlueprint<'w, 's> {
+    /// spawn_blueprint places a blueprint for haulers and builders to work on
+    fn spawn_blueprint<'a>(&'a mut self, b: Blueprint, position: Vec3) -> EntityCommands<'w, 's, 'a>;
+}
+
+impl<'w, 's> SpawnBlueprint<'w, 's> for Commands<'w, 's> {
+    fn spawn_blueprint<'a>(&'a mut self, b: Blueprint, position: Vec3) -> EntityCommands<'w, 's, 'a> {
+        let mut base = self.spawn();
+        base.insert(b)
+            // the materials are checked against the cost, weight doesn't matter here
+            .insert(Inventory::new(f32::MAX))
+            .insert(Transform::from_translation(position))
+            .insert(GlobalTransform::identity());
+        base
+    }
+}
+
+/// plan_construction queues a build job for every blueprint that has its materials
+fn plan_construction(mut jobs: ResMut<Jobs>, mut q: Query<(Entity, &mut Blueprint, &Inventory)>) {
+    for (e, mut bp, inv) in q.iter_mut() {
+        if bp.job != NO_JOB {
+            if jobs.get(bp.job).is_some() {
+                continue;
+            }
+            bp.job = NO_JOB;
+        }
+        if bp.data().is_none() || !bp.missing(inv).is_empty() {
+            continue;
+        }
+        bp.job = jobs.push(JobKind::Build, BUILD_PRIORITY, Some(e));
+    }
+}
+
+/// work_construction swaps the blueprint for the finished building when the work is done
+fn work_construction(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut jobs: ResMut<Jobs>,
+    mut xp: EventWriter<ExperienceEvent>,
+    mut livings: Query<(Entity, &mut Living, Option<&Body>, Option<&Progression>, Option<&Equipment>)>,
+    mut blueprints: Query<(Entity, &mut Blueprint, &Transform)>,
+) {
+    let dt = time.delta_seconds();
+    for (e, mut l, body, p, eq) in livings.iter_mut() {
+        let j = match jobs.get(l.has_job) {
+            Some(j) if j.kind == JobKind::Build => j.clone(),
+            _ => continue,
+        };
+        let (be, mut bp, t) = match j.target.and_then(|t| blueprints.get_mut(t).ok()) {
+            Some(b) => b,
+            None => {
+                jobs.finish(j.id);
+                l.has_job = NO_JOB;
+                continue;
+            }
+        };
+        if t.translation.distance(l.position) > REACH {
+            continue;
+        }
+        let bd = match bp.data() {
+            Some(bd) => bd,
+            None => continue,
+        };
+
+        let skill = l.attributes(p, eq).skill(BUILD_SKILL);
+        bp.progress += skill.max(1.) * body.map(|b| b.manipulation()).unwrap_or(1.) * dt;
+        if bp.progress < bd.base_time as f32 {
+            continue;
+        }
+
+        if let Some(b) = Building::new(&bp.building, bp.material.clone()) {
+            commands.spawn_building(b, t.translation);
+        }
+        commands.entity(be).despawn_recursive();
+        xp.send(ExperienceEvent {
+            entity: e,
+            skill: BUILD_SKILL.to_string(),
+            amount: bd.base_time as f32,
+        });
+        jobs.finish(j.id);
+        l.has_job = NO_JOB;
+    }
+}
@@ -0,0 +1,202 @@
+use std::sync::{Arc, RwLock};
+use bevy::utils::{StableHashMap, StableHashSet};
+use lazy_static::*;
+use crate::sim::world::{
+    data::{entry_error, report, CategoryData, ManifestType},
+    item::OrganicSlot,
+};
+
+lazy_static! {
+    pub static ref Categories: CategoryRegistry = Default::default();
+}
+
+/// CategoryRegistry holds the current category tree, it's swapped out whole when the
+/// category files change
+#[derive(Default)]
+pub struct CategoryRegistry {
+    current: RwLock<Arc<CategoryTree>>,
+}
+
+impl CategoryRegistry {
+    pub fn tree(&self) -> Arc<CategoryTree> {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.tree().contains(name)
+    }
+
+    /// replace builds a new tree from the categories. A broken tree is reported and the
+    /// old one is kept.
+    pub fn replace(&self, categories: StableHashMap<String, CategoryData>) {
+        match CategoryTree::new(categories) {
+            Ok(t) => *self.current.write().unwrap() = Arc::new(t),
+            Err(e) => report(entry_error(&ManifestType::CategoryData, e.category(), e.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CategoryError {
+    UnknownParent { category: String, parent: String },
+    Cycle(String),
+}
+
+impl CategoryError {
+    /// category is the category the error was found at
+    pub fn category(&self) -> &str {
+        match self {
+            CategoryError::UnknownParent { category, .. } => category,
+            CategoryError::Cycle(c) => c,
+        }
+    }
+}
+
+impl std::fmt::Display for CategoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CategoryError::UnknownParent { parent, .. } => write!(f, "unknown parent category '{}'", parent),
+            CategoryError::Cycle(c) => write!(f, "'{}' is its own ancestor", c),
+        }
+    }
+}
+
+/// CategoryTree is the hierarchy of item categories. A category can have more than one
+/// parent, but following parents never leads back to where it started.
+#[derive(Debug, Default, Clone)]
+pub struct CategoryTree {
+    categories: StableHashMap<String, CategoryData>,
+}
+
+impl CategoryTree {
+    /// new builds the tree, failing on parents that don't exist and on cycles
+    pub fn new(categories: StableHashMap<String, CategoryData>) -> Result<Self, CategoryError> {
+        for c in categories.values() {
+            for p in c.parents() {
+                if !categories.contains_key(p) {
+                    return Err(CategoryError::UnknownParent {
+                        category: c.name.clone(),
+                        parent: p.clone(),
+                    });
+                }
+            }
+        }
+        let t = Self { categories };
+        for name in t.categories.keys() {
+            let mut path = vec![];
+            t.check_cycle(name, &mut path)?;
+        }
+        Ok(t)
+    }
+
+    fn check_cycle<'a>(&'a self, name: &'a String, path: &mut Vec<&'a String>) -> Result<(), CategoryError> {
+        if path.contains(&name) {
+            return Err(CategoryError::Cycle(name.clone()));
+        }
+        path.push(name);
+        if let Some(c) = self.categories.get(name) {
+            for p in c.parents() {
+                self.check_cycle(p, path)?;
+            }
+        }
+        path.pop();
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CategoryData> {
+        self.categories.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.categories.contains_key(name)
+    }
+
+    /// ancestors is every category above the given one
+    pub fn ancestors(&self, name: &str) -> StableHashSet<String> {
+        let mut out: StableHashSet<String> = Default::default();
+        let mut open: Vec<&str> = vec![name];
+        while let Some(n) = open.pop() {
+            if let Some(c) = self.categories.get(n) {
+                for p in c.parents() {
+                    if out.insert(p.clone()) {
+                        open.push(p);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// is_a is true when the category is the other one, or anywhere below it
+    pub fn is_a(&self, name: &str, other: &str) -> bool {
+        name == other || self.ancestors(name).contains(other)
+    }
+
+    /// children are the categories directly below the given one
+    pub fn children(&self, name: &str) -> Vec<&CategoryData> {
+        self.categories.values()
+            .filter(|c| c.parents().iter().any(|p| p == name))
+            .collect()
+    }
+
+    /// target is the slot items of the category are worn in, inherited from the parents
+    /// when the category doesn't set one
+    pub fn target(&self, name: &str) -> Option<OrganicSlot> {
+        let c = self.categories.get(name)?;
+        if c.target.is_some() {
+            return c.target;
+        }
+        c.parents().iter().find_map(|p| self.target(p))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(categories: &[(&str, &[&str])]) -> Result<CategoryTree, CategoryError> {
+        let categories = categories.iter()
+            .map(|(name, parents)| {
+                let c = CategoryData {
+                    name: name.to_string(),
+                    parents: Some(parents.iter().map(|p| p.to_string()).collect()),
+                    ..Default::default()
+                };
+                (name.to_string(), c)
+            })
+            .collect();
+        CategoryTree::new(categories)
+    }
+
+    #[test]
+    fn is_a_follows_every_parent() {
+        let t = tree(&[
+            ("gear", &[]),
+            ("weapons", &["gear"]),
+            ("tools", &["gear"]),
+            ("axes", &["weapons", "tools"]),
+        ]).unwrap();
+        assert!(t.is_a("axes", "axes"));
+        assert!(t.is_a("axes", "tools"));
+        assert!(t.is_a("axes", "gear"));
+        assert!(!t.is_a("tools", "axes"));
+        assert!(!t.is_a("weapons", "tools"));
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let err = tree(&[
+            ("gear", &["axes"]),
+            ("tools", &["gear"]),
+            ("axes", &["tools"]),
+        ]).unwrap_err();
+        assert!(matches!(err, CategoryError::Cycle(_)));
+        assert!(matches!(tree(&[("gear", &["gear"])]), Err(CategoryError::Cycle(c)) if c == "gear"));
+    }
+
+    #[test]
+    fn unknown_parents_are_rejected() {
+        let err = tree(&[("axes", &["tools"])]).unwrap_err();
+        assert_eq!(err, CategoryError::UnknownParent { category: "axes".to_string(), parent: "tools".to_string() });
+    }
+}
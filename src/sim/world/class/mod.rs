@@ -1,12 +1,42 @@
-// Class determines a characters focus in life
+use bevy::prelude::*;
+use rand::Rng;
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use crate::sim::{
+    generator::SimRng,
+    world::{
+        data::{Classes, ClassData, Cores},
+        entity,
+    },
+};
+
+/// System hands out experience and promotes livings that earned it
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ExperienceEvent>()
+            .add_system(roll_cores)
+            .add_system(gain_experience);
+    }
+}
 
-enum Class {
+// experience from skills the class doesn't care about is scaled by this
+const OFF_CLASS_XP: f32 = 0.25;
+
+// Class determines a characters focus in life
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum Class {
     Pilot,
+    #[default]
     Builder,
     Miner
 }
 
-enum ExperienceModifier {
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum ExperienceModifier {
+    #[default]
     Rookie,
     Novice,
     Veteran,
@@ -16,7 +46,9 @@ enum ExperienceModifier {
     Legendary,
 }
 
-enum Modifier {
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum Modifier {
+    #[default]
     None,
     Forgotten,
     Battle,
@@ -25,4 +57,126 @@ enum Modifier {
     PyroCore,
     DualCore,
     ShockCore,
-}
\ No newline at end of file
+}
+
+/// Ability is something special a core modifier lets a living do
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum Ability {
+    // better at piloting mechs
+    MechSync,
+    // jump over obstacles
+    Flight,
+    // attacks set things on fire
+    Ignite,
+    // attacks stun
+    Overcharge,
+    // works two jobs at once
+    DoubleTime,
+    // shrugs off injuries
+    Fortitude,
+    // remembers nothing, but learns quickly
+    BlankSlate,
+}
+
+/// ExperienceEvent gives experience in a skill to a living
+#[derive(Debug, Clone)]
+pub struct ExperienceEvent {
+    pub entity: Entity,
+    pub skill: String,
+    pub amount: f32,
+}
+
+/// UnrolledCore holds the cores a living's race can have until one is rolled for it
+#[derive(Debug, Clone)]
+pub struct UnrolledCore(pub Vec<Modifier>);
+
+/// Progression is the class, rank and experience of a living
+#[derive(Debug, Default, Clone)]
+pub struct Progression {
+    pub class: Class,
+    pub rank: ExperienceModifier,
+    pub xp: f32,
+    pub core: Modifier,
+}
+
+impl Progression {
+    pub fn new(class: Class) -> Self {
+        Self {
+            class,
+            ..Default::default()
+        }
+    }
+
+    pub fn data(&self) -> Option<Arc<ClassData>> {
+        Classes.get(&ClassData::key(self.class))
+    }
+
+    /// gain adds experience, class skills give full experience. Returns the new rank
+    /// when the living was promoted.
+    pub fn gain(&mut self, skill: &str, amount: f32) -> Option<ExperienceModifier> {
+        let cd = self.data()?;
+        let mut amount = if cd.skills.iter().any(|s| s == skill) {
+            amount
+        } else {
+            amount * OFF_CLASS_XP
+        };
+        if self.abilities().contains(&Ability::BlankSlate) {
+            amount *= 1.5;
+        }
+        self.xp += amount;
+
+        let earned = cd.ranks.iter()
+            .filter(|r| r.xp <= self.xp)
+            .map(|r| r.rank)
+            .max()?;
+        if earned > self.rank {
+            self.rank = earned;
+            return Some(earned);
+        }
+        None
+    }
+
+    /// bonuses are the modifiers of every rank reached so far
+    pub fn bonuses(&self) -> Vec<entity::Modifier> {
+        self.data()
+            .map(|cd| {
+                cd.ranks.iter()
+                    .filter(|r| r.rank <= self.rank)
+                    .flat_map(|r| r.bonuses.iter().cloned())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// abilities are granted by the core modifier
+    pub fn abilities(&self) -> Vec<Ability> {
+        Cores.get(&format!("{:?}", self.core))
+            .map(|c| c.abilities.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// roll_cores picks the core of newly spawned livings, with the sim's rng so it's the
+/// same for the same seed
+fn roll_cores(
+    mut commands: Commands,
+    mut rng: ResMut<SimRng>,
+    mut q: Query<(Entity, &UnrolledCore, &mut Progression)>,
+) {
+    for (e, u, mut p) in q.iter_mut() {
+        if !u.0.is_empty() {
+            p.core = u.0[rng.0.gen_range(0..u.0.len())];
+        }
+        commands.entity(e).remove::<UnrolledCore>();
+    }
+}
+
+fn gain_experience(mut evs: EventReader<ExperienceEvent>, mut q: Query<&mut Progression>) {
+    for e in evs.iter() {
+        if let Ok(mut p) = q.get_mut(e.entity) {
+            if let Some(r) = p.gain(&e.skill, e.amount) {
+                info!("{:?} has been promoted to {:?} {:?}", e.entity, r, p.class);
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadState, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::StableHashMap,
+};
+use std::{ops::Deref, path::Path, sync::Arc};
+use crate::sim::world::{
+    category::Categories,
+    data::*,
+};
+
+/// System loads the data files through the asset server into the registries and, while
+/// developing, builds them again whenever a file changes on disk
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<DataFile>()
+            .init_asset_loader::<DataFileLoader>()
+            .init_resource::<DataFiles>()
+            .init_resource::<EntityData>()
+            .init_resource::<ManifestReport>()
+            .add_event::<DataChanged>()
+            .init_resource::<Data<BuildingData>>()
+            .init_resource::<Data<LivingData>>()
+            .init_resource::<Data<RaceData>>()
+            .init_resource::<Data<MechPartData>>()
+            .init_resource::<Data<ClassData>>()
+            .init_resource::<Data<CoreData>>()
+            .init_resource::<Data<ItemData>>()
+            .init_resource::<Data<MaterialData>>()
+            .init_resource::<Data<RecipeData>>()
+            .init_resource::<Data<entity::Trait>>()
+            .init_resource::<Data<SkillCapabilityData>>()
+            .insert_resource(Categories.tree())
+            .add_startup_system(load_data_files)
+            .add_system(build_data);
+    }
+}
+
+/// Data are the entries of a registry as they were last built, as a resource for systems
+/// that want to say what data they use
+pub struct Data<T>(pub Entries<T>);
+
+impl<T> Default for Data<T> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+impl<T> Deref for Data<T> {
+    type Target = StableHashMap<String, Arc<T>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// DataFile is a parsed manifest or data file. The entries are only checked against their
+/// type once every file of the kind is in, see build_registry.
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "8b0c3e5e-6f37-4d1e-9a51-3f0d5c2b7e41"]
+pub struct DataFile {
+    pub kind: ManifestType,
+    pub text: String,
+    pub entries: Vec<serde_yaml::Value>,
+}
+
+#[derive(Default)]
+pub struct DataFileLoader;
+
+impl AssetLoader for DataFileLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let path = load_context.path();
+            let text = String::from_utf8(bytes.to_vec())?;
+            let file = if content_roots().iter().any(|r| Path::new(&r.manifest) == path) {
+                DataFile { kind: ManifestType::ManifestData, text, entries: vec![] }
+            } else {
+                let kind = content_files().into_iter()
+                    .find(|f| Path::new(&f.path) == path)
+                    .map(|f| f.kind)
+                    .ok_or_else(|| anyhow::anyhow!("{} isn't listed in any manifest", path.display()))?;
+                let path = path.to_string_lossy().to_string();
+                clear_reports(&path);
+                // errors are reported the same way as from the command line tools
+                let entries = parse_entries(&path, &text).map_err(|e| {
+                    report(e.clone());
+                    anyhow::anyhow!("{}", e)
+                })?;
+                DataFile { kind, text, entries }
+            };
+            load_context.set_default_asset(LoadedAsset::new(file));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["yml", "yaml", "ron"]
+    }
+}
+
+/// DataChanged is sent after a registry was built again, anything holding on to values
+/// from it should look them up again
+#[derive(Debug, Clone)]
+pub struct DataChanged {
+    pub kind: ManifestType,
+}
+
+/// DataFiles are the handles of the manifest of every content root and every file
+/// they list
+#[derive(Default)]
+pub struct DataFiles {
+    pub manifests: Vec<Handle<DataFile>>,
+    pub files: Vec<(ContentFile, Handle<DataFile>)>,
+    // set once every registry was built for the first time
+    pub loaded: bool,
+    // the kinds with files that changed since they were last built
+    dirty: Vec<ManifestType>,
+}
+
+fn load_data_files(server: Res<AssetServer>, mut files: ResMut<DataFiles>) {
+    #[cfg(debug_assertions)]
+    if let Err(e) = server.watch_for_changes() {
+        warn!("data files won't hot reload: {:?}", e);
+    }
+    watch_manifests(&server, &mut files, content_roots());
+}
+
+/// watch_manifests loads every manifest and the files they list. The manifests are read
+/// straight from disk as well, since the list is needed right away.
+fn watch_manifests(server: &AssetServer, files: &mut DataFiles, roots: Vec<ContentRoot>) {
+    files.manifests = roots.iter().map(|r| server.load(r.manifest.as_str())).collect();
+    files.files = content_files().into_iter()
+        .map(|f| {
+            let h = server.load(f.path.as_str());
+            (f, h)
+        })
+        .collect();
+    // files that were dropped from a manifest take their entries with them
+    files.dirty = ManifestType::load_order();
+}
+
+/// build_data builds the registry of every kind with a file that changed, once none of
+/// the files of those kinds are still loading. Files that failed to load are left out,
+/// their errors are already reported.
+fn build_data(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    assets: Res<Assets<DataFile>>,
+    mut files: ResMut<DataFiles>,
+    mut models: ResMut<EntityData>,
+    mut report: ResMut<ManifestReport>,
+    mut evs: EventReader<AssetEvent<DataFile>>,
+    mut changed: EventWriter<DataChanged>,
+) {
+    for e in evs.iter() {
+        let h = match e {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { .. } => continue,
+        };
+        if files.manifests.contains(h) {
+            if let AssetEvent::Modified { .. } = e {
+                watch_manifests(&server, &mut files, reload_content_roots());
+            }
+            continue;
+        }
+        let kinds: Vec<ManifestType> = files.files.iter()
+            .filter(|(_, fh)| fh == h)
+            .map(|(f, _)| f.kind.clone())
+            .collect();
+        for kind in kinds {
+            if !files.dirty.contains(&kind) {
+                files.dirty.push(kind);
+            }
+        }
+    }
+    if files.dirty.is_empty() {
+        return;
+    }
+    let loading = files.files.iter()
+        .filter(|(f, _)| files.dirty.contains(&f.kind))
+        .any(|(_, h)| !matches!(server.get_load_state(h), LoadState::Loaded | LoadState::Failed));
+    if loading {
+        // building now would lose the entries of the files that aren't in yet
+        return;
+    }
+
+    let mut built = vec![];
+    for kind in ManifestType::load_order() {
+        if !files.dirty.contains(&kind) {
+            continue;
+        }
+        let sources: Vec<Source> = files.files.iter()
+            .filter(|(f, _)| f.kind == kind)
+            // a file that failed to reload still has its old contents
+            .filter(|(_, h)| server.get_load_state(h) == LoadState::Loaded)
+            .filter_map(|(f, h)| {
+                let d = assets.get(h)?;
+                Some(Source { root: &f.root, path: &f.path, text: &d.text, entries: &d.entries })
+            })
+            .collect();
+        if rebuild(&kind, &sources) {
+            publish(&mut commands, &kind);
+            built.push(kind);
+        }
+    }
+    files.dirty.clear();
+
+    if built.contains(&ManifestType::BuildingData) || built.contains(&ManifestType::LivingData) {
+        *models = EntityData::new(&server);
+    }
+    *report = ManifestReport::current();
+    for kind in built {
+        if files.loaded {
+            info!("reloaded {:?}", kind);
+        }
+        changed.send(DataChanged { kind });
+    }
+    files.loaded = true;
+}
+
+/// publish swaps the resource of the kind for the registry as it is now
+fn publish(commands: &mut Commands, kind: &ManifestType) {
+    match kind {
+        ManifestType::BuildingData => commands.insert_resource(Data(Buildings.entries())),
+        ManifestType::LivingData => commands.insert_resource(Data(Entities.entries())),
+        ManifestType::RaceData => commands.insert_resource(Data(Races.entries())),
+        ManifestType::MechPartData => commands.insert_resource(Data(MechParts.entries())),
+        ManifestType::ClassData => commands.insert_resource(Data(Classes.entries())),
+        ManifestType::CoreData => commands.insert_resource(Data(Cores.entries())),
+        ManifestType::ItemData => commands.insert_resource(Data(Items.entries())),
+        ManifestType::CategoryData => commands.insert_resource(Categories.tree()),
+        ManifestType::MaterialData => commands.insert_resource(Data(Materials.entries())),
+        ManifestType::RecipeData => commands.insert_resource(Data(Recipes.entries())),
+        ManifestType::TraitData => commands.insert_resource(Data(Traits.entries())),
+        ManifestType::SkillCapabilityData => commands.insert_resource(Data(SkillCapabilities.entries())),
+        ManifestType::ManifestData => {}
+    }
+}
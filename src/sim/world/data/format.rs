@@ -0,0 +1,494 @@
+use serde::de::DeserializeOwned;
+use crate::sim::world::data::*;
+
+/// Format is how a data file is written, picked by its extension. Anything that isn't
+/// .ron is read as YAML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Yaml,
+    Ron,
+}
+
+impl Format {
+    pub fn of(path: &str) -> Self {
+        match std::path::Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("ron") => Format::Ron,
+            _ => Format::Yaml,
+        }
+    }
+}
+
+/// parse reads a whole data file in its format. RON is read into the same values YAML
+/// is, see RonReader, so both formats load the same way.
+pub fn parse<T: DeserializeOwned>(path: &str, text: &str) -> Result<T, ManifestError> {
+    match Format::of(path) {
+        Format::Yaml => serde_yaml::from_str(text).map_err(|e| ManifestError::from_yaml(path, &e)),
+        Format::Ron => {
+            let v = RonReader::new(path, text).read()?;
+            serde_yaml::from_value(v).map_err(|e| ManifestError::new(path, e.to_string()))
+        }
+    }
+}
+
+/// to_ron converts a YAML data file to RON. The entries go through the same loose values
+/// they're loaded as, and the result is read back to make sure nothing changed.
+pub fn to_ron(path: &str, text: &str) -> Result<String, ManifestError> {
+    let file: ManifestFile<serde_yaml::Value> = parse(path, text)?;
+    let out = ron::ser::to_string_pretty(&file, Default::default())
+        .map_err(|e| ManifestError::new(path, e.to_string()))?;
+    let ron_path = std::path::Path::new(path).with_extension("ron").to_string_lossy().to_string();
+    let back: ManifestFile<serde_yaml::Value> = parse(&ron_path, &out)?;
+    let changed = file.entries.len() != back.entries.len()
+        || file.entries.iter().zip(back.entries.iter()).any(|(a, b)| !same(a, b));
+    if changed {
+        return Err(ManifestError::new(path, "doesn't read back the same as RON"));
+    }
+    Ok(out)
+}
+
+/// convert_to_ron writes the RON version of a YAML data file next to it and returns its
+/// path. The manifest still points at the YAML file until it's changed by hand.
+pub fn convert_to_ron(path: &str) -> Result<String, ManifestError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| ManifestError::new(path, e.to_string()))?;
+    let out = to_ron(path, &text)?;
+    let to = std::path::Path::new(path).with_extension("ron").to_string_lossy().to_string();
+    std::fs::write(&to, out).map_err(|e| ManifestError::new(to.as_str(), e.to_string()))?;
+    Ok(to)
+}
+
+// RON writes whole floats without the fraction, so numbers are compared by value
+fn same(a: &serde_yaml::Value, b: &serde_yaml::Value) -> bool {
+    use serde_yaml::Value;
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        (Value::Sequence(x), Value::Sequence(y)) => {
+            x.len() == y.len() && x.iter().zip(y.iter()).all(|(x, y)| same(x, y))
+        }
+        (Value::Mapping(x), Value::Mapping(y)) => {
+            x.len() == y.len() && x.iter().all(|(k, v)| y.get(k).map(|w| same(v, w)).unwrap_or(false))
+        }
+        _ => a == b,
+    }
+}
+
+/// RonReader reads RON text into the values serde_yaml would have made from the same data
+/// written as YAML. ron itself can't be used for that, it drops the name of a bare enum
+/// variant when it doesn't know the type it's reading, so `body: Organic` would load as
+/// nothing.
+///
+/// - a bare identifier is a unit variant and becomes its name, `Organic` is `"Organic"`
+/// - `Some(x)` is `x` and `None` is null
+/// - `(a: 1)` is a map, the struct name in `Name(a: 1)` is left out like YAML does
+/// - `(1, 2)` is a list, and `()` is null
+/// - `Name(x)` is a newtype variant and becomes `{Name: x}`, `Name(x, y)` is `{Name: [x, y]}`
+struct RonReader<'a> {
+    path: &'a str,
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RonReader<'a> {
+    fn new(path: &'a str, text: &'a str) -> Self {
+        Self {
+            path,
+            text: text.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn read(mut self) -> Result<serde_yaml::Value, ManifestError> {
+        self.skip_ws()?;
+        // extensions like #![enable(implicit_some)] change nothing here
+        while self.text[self.pos..].starts_with(b"#!") {
+            while !matches!(self.peek(), None | Some(b'\n')) {
+                self.pos += 1;
+            }
+            self.skip_ws()?;
+        }
+        let v = self.value()?;
+        self.skip_ws()?;
+        match self.peek() {
+            None => Ok(v),
+            Some(_) => Err(self.error("trailing characters")),
+        }
+    }
+
+    // the error at the current position, lines and columns start at 1
+    fn error<M: Into<String>>(&self, message: M) -> ManifestError {
+        let before = &self.text[..self.pos.min(self.text.len())];
+        let line = before.iter().filter(|b| **b == b'\n').count() + 1;
+        let start = before.iter().rposition(|b| *b == b'\n').map(|i| i + 1).unwrap_or(0);
+        let column = String::from_utf8_lossy(&before[start..]).chars().count() + 1;
+        ManifestError::new(self.path, message).at(line, column)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, b: u8) -> Result<(), ManifestError> {
+        self.skip_ws()?;
+        if self.peek() != Some(b) {
+            return Err(self.error(format!("expected '{}'", b as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn skip_ws(&mut self) -> Result<(), ManifestError> {
+        loop {
+            match self.peek() {
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(b'/') if self.text.get(self.pos + 1) == Some(&b'/') => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.pos += 1;
+                    }
+                }
+                Some(b'/') if self.text.get(self.pos + 1) == Some(&b'*') => {
+                    let end = self.text[self.pos + 2..].windows(2).position(|w| w == b"*/");
+                    match end {
+                        Some(i) => self.pos += i + 4,
+                        None => return Err(self.error("unterminated comment")),
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<serde_yaml::Value, ManifestError> {
+        use serde_yaml::Value;
+        self.skip_ws()?;
+        match self.peek() {
+            None => Err(self.error("expected a value")),
+            Some(b'[') => {
+                self.pos += 1;
+                let mut list = vec![];
+                while !self.end_of(b']')? {
+                    list.push(self.value()?);
+                    self.comma_or(b']')?;
+                }
+                Ok(Value::Sequence(list))
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let mut map = serde_yaml::Mapping::new();
+                while !self.end_of(b'}')? {
+                    let k = self.value()?;
+                    self.expect(b':')?;
+                    let v = self.value()?;
+                    map.insert(k, v);
+                    self.comma_or(b'}')?;
+                }
+                Ok(Value::Mapping(map))
+            }
+            Some(b'(') => Ok(self.parens()?.into_value()),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'r') if matches!(self.text.get(self.pos + 1), Some(b'"') | Some(b'#')) => {
+                self.raw_string().map(Value::String)
+            }
+            Some(b'\'') => self.char().map(|c| Value::String(c.to_string())),
+            Some(b) if b == b'-' || b == b'+' || b == b'.' || b.is_ascii_digit() => self.number(),
+            Some(b) if b == b'_' || b.is_ascii_alphabetic() => {
+                let ident = self.ident();
+                match ident.as_str() {
+                    "true" => return Ok(Value::Bool(true)),
+                    "false" => return Ok(Value::Bool(false)),
+                    "None" => return Ok(Value::Null),
+                    "inf" => return Ok(Value::from(f64::INFINITY)),
+                    "NaN" => return Ok(Value::from(f64::NAN)),
+                    _ => (),
+                }
+                self.skip_ws()?;
+                if self.peek() != Some(b'(') {
+                    return Ok(Value::String(ident));
+                }
+                let inner = match self.parens()? {
+                    // a named struct, the name doesn't matter
+                    Parens::Fields(m) => return Ok(Value::Mapping(m)),
+                    Parens::Unit => return Ok(Value::String(ident)),
+                    Parens::Items(mut items) if items.len() == 1 => items.remove(0),
+                    Parens::Items(items) => Value::Sequence(items),
+                };
+                if ident == "Some" {
+                    return Ok(inner);
+                }
+                let mut map = serde_yaml::Mapping::new();
+                map.insert(Value::String(ident), inner);
+                Ok(Value::Mapping(map))
+            }
+            Some(b) => Err(self.error(format!("unexpected '{}'", b as char))),
+        }
+    }
+
+    // end_of is true and steps past the closing bracket if it's next
+    fn end_of(&mut self, close: u8) -> Result<bool, ManifestError> {
+        self.skip_ws()?;
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok(true);
+        }
+        if self.peek().is_none() {
+            return Err(self.error(format!("expected '{}'", close as char)));
+        }
+        Ok(false)
+    }
+
+    // comma_or steps past the comma after an item, the last item doesn't need one
+    fn comma_or(&mut self, close: u8) -> Result<(), ManifestError> {
+        self.skip_ws()?;
+        match self.peek() {
+            Some(b',') => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(b) if b == close => Ok(()),
+            _ => Err(self.error(format!("expected ',' or '{}'", close as char))),
+        }
+    }
+
+    // parens reads what's between ( and ), struct fields if the first item is a field name
+    fn parens(&mut self) -> Result<Parens, ManifestError> {
+        self.expect(b'(')?;
+        if self.end_of(b')')? {
+            return Ok(Parens::Unit);
+        }
+        if !self.at_field() {
+            let mut items = vec![];
+            while !self.end_of(b')')? {
+                items.push(self.value()?);
+                self.comma_or(b')')?;
+            }
+            return Ok(Parens::Items(items));
+        }
+        let mut fields = serde_yaml::Mapping::new();
+        while !self.end_of(b')')? {
+            if !self.at_field() {
+                return Err(self.error("expected a field name"));
+            }
+            let name = self.ident();
+            self.expect(b':')?;
+            let v = self.value()?;
+            fields.insert(serde_yaml::Value::String(name), v);
+            self.comma_or(b')')?;
+        }
+        Ok(Parens::Fields(fields))
+    }
+
+    // at_field is true when an identifier and a colon are next
+    fn at_field(&mut self) -> bool {
+        let start = self.pos;
+        let ident = self.ident();
+        let found = !ident.is_empty() && self.skip_ws().is_ok() && self.peek() == Some(b':');
+        self.pos = start;
+        found
+    }
+
+    fn ident(&mut self) -> String {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b) if b == b'_' || b.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        String::from_utf8_lossy(&self.text[start..self.pos]).to_string()
+    }
+
+    fn number(&mut self) -> Result<serde_yaml::Value, ManifestError> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b) if b.is_ascii_alphanumeric() || b"+-._".contains(&b)) {
+            self.pos += 1;
+        }
+        let text = String::from_utf8_lossy(&self.text[start..self.pos]).replace('_', "");
+        let digits = text.trim_start_matches('+');
+        if let Ok(i) = digits.parse::<i64>() {
+            return Ok(serde_yaml::Value::from(i));
+        }
+        if let Ok(f) = digits.parse::<f64>() {
+            return Ok(serde_yaml::Value::from(f));
+        }
+        self.pos = start;
+        Err(self.error(format!("'{}' isn't a number", text)))
+    }
+
+    fn string(&mut self) -> Result<String, ManifestError> {
+        self.expect(b'"')?;
+        let mut out = vec![];
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some(b'"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let c = self.escape()?;
+                    out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                Some(b) => {
+                    out.push(b);
+                    self.pos += 1;
+                }
+            }
+        }
+        String::from_utf8(out).map_err(|_| self.error("a string isn't valid UTF-8"))
+    }
+
+    // escape reads what comes after a backslash
+    fn escape(&mut self) -> Result<char, ManifestError> {
+        let b = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+        self.pos += 1;
+        Ok(match b {
+            b'n' => '\n',
+            b't' => '\t',
+            b'r' => '\r',
+            b'0' => '\0',
+            b'\\' => '\\',
+            b'"' => '"',
+            b'\'' => '\'',
+            b'u' => {
+                self.expect(b'{')?;
+                let start = self.pos;
+                while matches!(self.peek(), Some(b) if b.is_ascii_hexdigit()) {
+                    self.pos += 1;
+                }
+                let hex = String::from_utf8_lossy(&self.text[start..self.pos]).to_string();
+                self.expect(b'}')?;
+                u32::from_str_radix(&hex, 16).ok()
+                    .and_then(std::char::from_u32)
+                    .ok_or_else(|| self.error(format!("'\\u{{{}}}' isn't a character", hex)))?
+            }
+            b => return Err(self.error(format!("unknown escape '\\{}'", b as char))),
+        })
+    }
+
+    // raw strings are r"..." or r#"..."#, with as many #s as needed
+    fn raw_string(&mut self) -> Result<String, ManifestError> {
+        self.pos += 1;
+        let hashes = self.text[self.pos..].iter().take_while(|b| **b == b'#').count();
+        self.pos += hashes;
+        self.expect(b'"')?;
+        let mut close = vec![b'"'];
+        close.extend(std::iter::repeat(b'#').take(hashes));
+        let end = self.text[self.pos..].windows(close.len()).position(|w| w == close.as_slice());
+        match end {
+            Some(i) => {
+                let s = String::from_utf8_lossy(&self.text[self.pos..self.pos + i]).to_string();
+                self.pos += i + close.len();
+                Ok(s)
+            }
+            None => Err(self.error("unterminated string")),
+        }
+    }
+
+    fn char(&mut self) -> Result<char, ManifestError> {
+        self.expect(b'\'')?;
+        let c = match self.peek() {
+            Some(b'\\') => {
+                self.pos += 1;
+                self.escape()?
+            }
+            Some(_) => {
+                let rest = std::str::from_utf8(&self.text[self.pos..])
+                    .map_err(|_| self.error("a character isn't valid UTF-8"))?;
+                let c = rest.chars().next().ok_or_else(|| self.error("unterminated character"))?;
+                self.pos += c.len_utf8();
+                c
+            }
+            None => return Err(self.error("unterminated character")),
+        };
+        self.expect(b'\'')?;
+        Ok(c)
+    }
+}
+
+/// Parens is what a RON ( ) holds, struct fields or tuple items
+enum Parens {
+    Unit,
+    Fields(serde_yaml::Mapping),
+    Items(Vec<serde_yaml::Value>),
+}
+
+impl Parens {
+    fn into_value(self) -> serde_yaml::Value {
+        match self {
+            Parens::Unit => serde_yaml::Value::Null,
+            Parens::Fields(m) => serde_yaml::Value::Mapping(m),
+            Parens::Items(items) => serde_yaml::Value::Sequence(items),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::world::body::BodyPlan;
+
+    // written by hand, the way RON is usually written
+    const RACES: &str = r#"
+#![enable(implicit_some)]
+(
+    entries: [
+        (
+            name: "Shape",
+            description: "What a weird race.",
+            properties: Some(["Orderable", "Living"]),
+            body: Some(Organic),
+            needs: (hunger: 0.05, rest: 0.03, comfort: 0.02),
+        ),
+        // the struct name is allowed and left out
+        RaceData(name: "Automaton", description: "Gears where the heart should be.", body: Mechanical),
+        /* no body */
+        (name: "Tree", description: r"They got what animals need."),
+    ],
+)
+"#;
+
+    const MATERIALS: &str = r#"
+(
+    entries: [
+        (
+            name: "Synthetic Biosteel",
+            id: "synthetic_biosteel",
+            properties: [
+                (attr: Damage, value: (15, 25)),
+                (attr: Durability, value: [15, 25]),
+            ],
+        ),
+    ],
+)
+"#;
+
+    #[test]
+    fn ron_keeps_enum_variants() {
+        let file: ManifestFile<RaceData> = parse("races.ron", RACES).unwrap();
+        let bodies: Vec<Option<BodyPlan>> = file.entries.iter().map(|r| r.body).collect();
+        assert_eq!(bodies, vec![Some(BodyPlan::Organic), Some(BodyPlan::Mechanical), None]);
+        assert_eq!(file.entries[0].properties, Some(vec!["Orderable".to_string(), "Living".to_string()]));
+        assert!(file.entries[0].needs.is_some());
+    }
+
+    #[test]
+    fn ron_entries_load_into_a_registry() {
+        let entries = parse_entries("materials.ron", MATERIALS).unwrap();
+        let source = Source { root: "base", path: "materials.ron", text: MATERIALS, entries: &entries };
+        let materials = build_registry::<MaterialData>(&[source]);
+        let attrs: Vec<MaterialAttr> = materials["synthetic_biosteel"].properties.iter().map(|p| p.attr).collect();
+        assert_eq!(attrs, vec![MaterialAttr::Damage, MaterialAttr::Durability]);
+        assert_eq!(materials["synthetic_biosteel"].properties[0].value, [15, 25]);
+    }
+
+    #[test]
+    fn converted_yaml_reads_back_the_same() {
+        let yaml = "entries:\n  - name: Shape\n    description: Odd\n    body: Organic\n";
+        let out = to_ron("races.yml", yaml).unwrap();
+        let file: ManifestFile<RaceData> = parse("races.ron", &out).unwrap();
+        assert_eq!(file.entries[0].body, Some(BodyPlan::Organic));
+    }
+
+    #[test]
+    fn ron_errors_have_a_location() {
+        let err = parse::<ManifestFile<RaceData>>("races.ron", "(\n  entries: [\n    (name: @),\n  ],\n)").unwrap_err();
+        assert_eq!((err.line, err.column), (Some(3), Some(12)));
+    }
+}
@@ -0,0 +1,88 @@
+use serde::{de, ser, Serialize, Serializer, Deserialize, Deserializer};
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use std::sync::Arc;
+use crate::sim::world::data::*;
+
+// content_id declares a typed id for the entries of one registry. The id is the interned
+// number, which is only stable for as long as the game runs, so it's written and read as
+// the string id. Reading an id that isn't loaded is an error.
+macro_rules! content_id {
+    ($(#[$doc:meta])* $name:ident, $registry:ident, $data:ty, $what:literal) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(pub u32);
+
+        impl $name {
+            /// of is the id of the loaded entry with the string id
+            pub fn of(id: &str) -> Option<Self> {
+                $registry.number(id).map(Self)
+            }
+
+            /// name is the string id from the data files
+            pub fn name(&self) -> Option<&'static str> {
+                $registry.name(self.0)
+            }
+
+            /// data is the entry, None once a reload removed it
+            pub fn data(&self) -> Option<Arc<$data>> {
+                $registry.by_number(self.0)
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self.name() {
+                    Some(n) => write!(f, "{}", n),
+                    None => write!(f, "#{}", self.0),
+                }
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                match self.name() {
+                    Some(n) => s.serialize_str(n),
+                    None => Err(ser::Error::custom(format!("{} #{} was never loaded", stringify!($name), self.0))),
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                let id = String::deserialize(d)?;
+                Self::of(&id).ok_or_else(|| de::Error::custom(format!("unknown {} '{}'", $what, id)))
+            }
+        }
+
+        impl JsonSchema for $name {
+            fn schema_name() -> String {
+                stringify!($name).to_string()
+            }
+
+            fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+                String::json_schema(gen)
+            }
+        }
+    };
+}
+
+content_id!(
+    /// BuildingId is a BuildingData entry
+    BuildingId, Buildings, BuildingData, "building"
+);
+content_id!(
+    /// LivingId is a LivingData entry
+    LivingId, Entities, LivingData, "living"
+);
+content_id!(
+    /// RaceId is a RaceData entry
+    RaceId, Races, RaceData, "race"
+);
+content_id!(
+    /// ItemId is an ItemData entry
+    ItemId, Items, ItemData, "item"
+);
+content_id!(
+    /// MaterialId is a MaterialData entry
+    MaterialId, Materials, MaterialData, "material"
+);
@@ -0,0 +1,154 @@
+use bevy::{prelude::warn, utils::StableHashMap};
+use lazy_static::*;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use crate::sim::world::data::*;
+
+// the language every string has to exist in, anything missing in another language
+// falls back to it
+pub const DEFAULT_LANGUAGE: &str = "en";
+// string tables are <language>.yml or .ron in here, in every content root
+pub const LOCALE_DIR: &str = "data/locale";
+const LANGUAGE_NAME_KEY: &str = "language.name";
+// the options that apply to every game, kept between runs
+const SETTINGS_PATH: &str = "settings.yml";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    language: Option<String>,
+}
+
+impl Settings {
+    // a missing file is the defaults, a broken one is only warned about since it's not
+    // game data
+    fn read() -> Self {
+        let text = match std::fs::read_to_string(SETTINGS_PATH) {
+            Ok(t) => t,
+            Err(_) => return Self::default(),
+        };
+        serde_yaml::from_str(&text).unwrap_or_else(|e| {
+            warn!("ignoring {}: {}", SETTINGS_PATH, e);
+            Self::default()
+        })
+    }
+
+    fn write(&self) {
+        let written = serde_yaml::to_string(self)
+            .map_err(|e| e.to_string())
+            .and_then(|text| std::fs::write(SETTINGS_PATH, text).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            warn!("couldn't save {}: {}", SETTINGS_PATH, e);
+        }
+    }
+}
+
+lazy_static! {
+    static ref Strings: RwLock<StringTables> = RwLock::new(StringTables::load());
+}
+
+/// StringTables are the strings of every language found in any content root. A later
+/// root replaces the strings of an earlier one, like it does with entries.
+pub struct StringTables {
+    pub language: String,
+    tables: StableHashMap<String, StableHashMap<String, String>>,
+}
+
+impl StringTables {
+    fn load() -> Self {
+        let mut tables: StableHashMap<String, StableHashMap<String, String>> = Default::default();
+        for root in content_roots() {
+            let files = std::fs::read_dir(root.dir.join(LOCALE_DIR)).into_iter()
+                .flatten()
+                .flatten()
+                .map(|f| f.path())
+                .filter(|p| p.extension().map(|e| e == "yml" || e == "yaml" || e == "ron").unwrap_or(false));
+            for p in files {
+                let lang = match p.file_stem() {
+                    Some(l) => l.to_string_lossy().to_string(),
+                    None => continue,
+                };
+                let path = p.to_string_lossy().to_string();
+                let strings = std::fs::read_to_string(&p)
+                    .map_err(|e| ManifestError::new(path.as_str(), e.to_string()))
+                    .and_then(|text| format::parse::<StableHashMap<String, String>>(&path, &text));
+                match strings {
+                    Ok(s) => tables.entry(lang).or_default().extend(s),
+                    Err(e) => report(e),
+                }
+            }
+        }
+        if !tables.contains_key(DEFAULT_LANGUAGE) {
+            report(ManifestError::new(LOCALE_DIR, format!("no strings for the default language '{}'", DEFAULT_LANGUAGE)));
+        }
+        // the saved language is only used while it still has strings
+        let language = Settings::read().language
+            .filter(|l| tables.contains_key(l))
+            .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string());
+        Self {
+            language,
+            tables,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&String> {
+        [self.language.as_str(), DEFAULT_LANGUAGE].iter()
+            .filter_map(|l| self.tables.get(*l))
+            .find_map(|t| t.get(key))
+    }
+}
+
+/// load reads the string tables now, so their errors are in the report
+pub(crate) fn load() {
+    lazy_static::initialize(&Strings);
+}
+
+/// tr is the text for a key in the current language. Data files can use plain text in
+/// place of a key, anything that isn't a key is shown as it is.
+pub fn tr(key: &str) -> String {
+    let key = key.trim();
+    Strings.read().unwrap().get(key).cloned().unwrap_or_else(|| key.to_string())
+}
+
+/// tr_with is tr filling in `{placeholder}`s. They're filled in after the lookup so every
+/// language can put them where it needs to.
+pub fn tr_with(key: &str, args: &[(&str, &str)]) -> String {
+    args.iter().fold(tr(key), |s, (k, v)| s.replace(&format!("{{{}}}", k), v))
+}
+
+/// languages are all languages with a string table, sorted
+pub fn languages() -> Vec<String> {
+    let mut langs: Vec<String> = Strings.read().unwrap().tables.keys().cloned().collect();
+    langs.sort();
+    langs
+}
+
+/// language_name is what a language calls itself, from the `language.name` string of
+/// its own table
+pub fn language_name(lang: &str) -> String {
+    Strings.read().unwrap().tables.get(lang)
+        .and_then(|t| t.get(LANGUAGE_NAME_KEY))
+        .cloned()
+        .unwrap_or_else(|| lang.to_string())
+}
+
+pub fn language() -> String {
+    Strings.read().unwrap().language.clone()
+}
+
+/// set_language switches every string to another language and remembers it for the next
+/// run, returns false if there are no strings for it
+pub fn set_language(lang: &str) -> bool {
+    {
+        let mut s = Strings.write().unwrap();
+        if !s.tables.contains_key(lang) {
+            return false;
+        }
+        s.language = lang.to_string();
+    }
+    // nothing can be translated while the strings are locked, so they aren't held for this
+    let mut settings = Settings::read();
+    settings.language = Some(lang.to_string());
+    settings.write();
+    true
+}
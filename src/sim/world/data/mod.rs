@@ -1,38 +1,97 @@
 use bevy::{
     asset::HandleId,
     gltf::Gltf,
-    prelude::{AssetServer, Res, ResMut, Scene, Handle},
+    prelude::{warn, AssetServer, ResMut, Scene, Handle, HandleUntyped},
     utils::HashMap
 };
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use serde_yaml::Deserializer;
 use std::{
     borrow::Borrow,
-    fs::File
+    fs::File,
+    sync::{Arc, Mutex, RwLock},
 };
 
 use lazy_static::*;
 use bevy::utils::StableHashMap;
+use crate::sim::world::{
+    body::{BodyPlan, InjuryKind},
+    category::Categories,
+    class::{Ability, Class, ExperienceModifier, Modifier},
+    entity,
+    item::{self, MechSlot, OrganicSlot},
+};
+use std::ops::{Add, AddAssign};
 
 // #[macro_use]
 // extern crate lazy_static;
 
 
+pub mod assets;
+pub mod validate;
+pub mod schema;
+pub mod format;
+pub mod ids;
+pub mod locale;
+
 lazy_static! {
-    pub static ref Buildings: StableHashMap<String, BuildingData> = load_manifest_data::<BuildingData>();
-    pub static ref Entities: StableHashMap<String, LivingData> = load_manifest_data::<LivingData>();
-    pub static ref Races: StableHashMap<String, RaceData> = load_manifest_data::<RaceData>();
+    // the registries start out empty. The game fills them from the data file assets, see
+    // assets, and the command line tools straight from disk, see load_from_disk.
+    pub static ref Buildings: Registry<BuildingData> = Default::default();
+    pub static ref Entities: Registry<LivingData> = Default::default();
+    pub static ref Races: Registry<RaceData> = Default::default();
+    pub static ref MechParts: Registry<MechPartData> = Default::default();
+    pub static ref Classes: Registry<ClassData> = Default::default();
+    pub static ref Cores: Registry<CoreData> = Default::default();
+    pub static ref Items: Registry<ItemData> = Default::default();
+    pub static ref Materials: Registry<MaterialData> = Default::default();
+    pub static ref Recipes: Registry<RecipeData> = Default::default();
+    pub static ref Traits: Registry<entity::Trait> = Default::default();
+    pub static ref SkillCapabilities: Registry<SkillCapabilityData> = Default::default();
 }
 
-#[derive(Default, Clone, Serialize, Deserialize)]
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RaceData{
     pub name: String,
     pub description: String,
-    pub properties: Option<Vec<String>>
+    pub properties: Option<Vec<String>>,
+    /// how quickly this race's needs decay, races without it don't have needs
+    pub needs: Option<NeedRates>,
+    /// the kind of body this race has, races without it can't be injured
+    pub body: Option<BodyPlan>,
+    /// the cores a living of this race can be born with, one is rolled when it spawns.
+    /// A core listed more than once is likelier.
+    #[serde(default)]
+    pub cores: Vec<Modifier>,
+    /// how tall a usual living of this race is, in centimeters
+    pub height: Option<f32>,
+    /// how heavy a usual living of this race is, in kilograms
+    pub weight: Option<f32>,
+}
+
+/// NeedRates are the points lost per second for each need
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NeedRates {
+    pub hunger: f32,
+    pub rest: f32,
+    pub comfort: f32,
+}
+
+impl Default for NeedRates {
+    fn default() -> Self {
+        Self{
+            hunger: 0.05,
+            rest: 0.03,
+            comfort: 0.02,
+        }
+    }
 }
 
 impl ManifestData for RaceData {
+    const ID_KEY: &'static str = "name";
+
     fn kind() -> ManifestType {
         ManifestType::RaceData
     }
@@ -42,17 +101,67 @@ impl ManifestData for RaceData {
     }
 }
 
-#[derive(Default, Clone, Serialize, Deserialize)]
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct LivingData{
     pub name: String,
     pub description: String,
     pub race: String,
     pub path: String,
     pub id: String,
+    /// what it leaves behind when it dies as a hostile
+    pub drops: Option<DropTable>,
     // pub tags: Vec<String>,
     // pub species: String
 }
 
+/// DropTable is rolled when a hostile dies, each roll picks one of the entries that
+/// apply, weighted by their weight
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DropTable {
+    #[serde(default = "default_drop_rolls")]
+    pub rolls: u32,
+    pub entries: Vec<DropEntry>,
+}
+
+fn default_drop_rolls() -> u32 {
+    1
+}
+
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DropEntry {
+    pub item: String,
+    #[serde(default = "default_drop_weight")]
+    pub weight: u32,
+    /// how many drop, between the two values before the world's drop rate is applied
+    pub count: [u32; 2],
+    /// the entry only applies when the condition holds
+    pub when: Option<DropCondition>,
+}
+
+fn default_drop_weight() -> u32 {
+    1
+}
+
+/// DropCondition limits an entry to deaths by a kind of injury, or to livings of at least
+/// some rank
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DropCondition {
+    pub cause: Option<InjuryKind>,
+    pub min_rank: Option<ExperienceModifier>,
+}
+
+impl DropCondition {
+    pub fn holds(&self, cause: Option<InjuryKind>, rank: Option<ExperienceModifier>) -> bool {
+        if self.cause.is_some() && self.cause != cause {
+            return false;
+        }
+        match self.min_rank {
+            Some(min) => rank.unwrap_or_default() >= min,
+            None => true,
+        }
+    }
+}
+
 impl LivingData {
     pub fn scene(&self) -> String {
         self.path.clone() + "#Scene0"
@@ -75,77 +184,793 @@ impl HasAsset for LivingData {
     }
 }
 
-#[derive(Clone)]
+// models are looked up in here. The asset root is the game's directory, so the data files
+// can be loaded as assets too.
+pub const MODEL_DIR: &str = "assets/";
+
+/// model_path is where the asset server finds a model named in the data
+pub fn model_path(path: &str) -> String {
+    format!("{}{}", MODEL_DIR, path)
+}
+
+/// EntityData are the model handles of every building and living thing
+#[derive(Clone, Default)]
 pub struct EntityData {
-    pub data: StableHashMap<&'static str, HandleId>,
+    pub buildings: StableHashMap<ids::BuildingId, HandleId>,
+    pub living: StableHashMap<ids::LivingId, HandleId>,
+    // keeps the models loaded, with the file each one is from
+    pub handles: Vec<(String, HandleUntyped)>,
 }
 
 impl EntityData {
-    pub fn new(asset_server: &Res<AssetServer>) -> Self{
-        let mut d: StableHashMap<&'static str, HandleId> = Default::default();
-        Buildings.iter().for_each(|(s, mut v)| {
-            let h = asset_server.load::<Gltf, &str>((v.path()).as_str());
-            d.insert(v.id.as_str(), h.id);
+    pub fn new(asset_server: &AssetServer) -> Self{
+        // entries often share a model, it's only kept once
+        let mut handles: Vec<(String, HandleUntyped)> = vec![];
+        let mut buildings: StableHashMap<ids::BuildingId, HandleId> = Default::default();
+        Buildings.entries().iter().for_each(|(s, v)| {
+            let h = asset_server.load::<Gltf, &str>(model_path(&v.path()).as_str());
+            if let Some(id) = ids::BuildingId::of(s) {
+                buildings.insert(id, h.id);
+            }
+            if !handles.iter().any(|(p, _)| *p == v.path()) {
+                handles.push((v.path(), h.clone_untyped()));
+            }
         });
-        Entities.iter().for_each(|(s, v)| {
-            let h = asset_server.load::<Gltf, &str>((v.path()).as_str());
-            d.insert(v.id.as_str(), h.id);
+        let mut living: StableHashMap<ids::LivingId, HandleId> = Default::default();
+        Entities.entries().iter().for_each(|(s, v)| {
+            let h = asset_server.load::<Gltf, &str>(model_path(&v.path()).as_str());
+            if let Some(id) = ids::LivingId::of(s) {
+                living.insert(id, h.id);
+            }
+            if !handles.iter().any(|(p, _)| *p == v.path()) {
+                handles.push((v.path(), h.clone_untyped()));
+            }
         });
         Self{
-            data: d,
+            buildings,
+            living,
+            handles,
+        }
+    }
+}
+
+/// ManifestError is a problem found while loading the data files. Loading carries on past
+/// it, so every problem can be reported at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestError {
+    pub path: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    // the id of the entry the error is in, if it's about a single entry
+    pub entry: Option<String>,
+    pub message: String,
+}
+
+impl ManifestError {
+    pub fn new<P: Into<String>, M: Into<String>>(path: P, message: M) -> Self {
+        Self {
+            path: path.into(),
+            line: None,
+            column: None,
+            entry: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn at(mut self, line: usize, column: usize) -> Self {
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+
+    pub fn entry<S: Into<String>>(mut self, id: S) -> Self {
+        self.entry = Some(id.into());
+        self
+    }
+
+    /// from_yaml keeps the location serde_yaml found the error at
+    pub(crate) fn from_yaml(path: &str, e: &serde_yaml::Error) -> Self {
+        let err = Self::new(path, e.to_string());
+        match e.location() {
+            Some(l) => err.at(l.line(), l.column()),
+            None => err,
+        }
+    }
+}
+
+impl std::fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let (Some(l), Some(c)) = (self.line, self.column) {
+            write!(f, ":{}:{}", l, c)?;
+        }
+        if let Some(id) = &self.entry {
+            write!(f, " ({})", id)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+lazy_static! {
+    // everything that went wrong loading the data, registries load lazily so this fills up
+    // as they are first used
+    static ref LoadErrors: Mutex<Vec<ManifestError>> = Default::default();
+}
+
+/// clear_reports forgets the errors of a file that's being loaded again
+pub(crate) fn clear_reports(path: &str) {
+    LoadErrors.lock().unwrap().retain(|e| e.path != path);
+}
+
+/// report records a loading error. The manifest is read by every registry, so the same
+/// error is only kept once.
+pub(crate) fn report(e: ManifestError) {
+    let mut errs = LoadErrors.lock().unwrap();
+    if !errs.contains(&e) {
+        warn!("{}", e);
+        errs.push(e);
+    }
+}
+
+/// ManifestReport is every error found loading the game data
+#[derive(Debug, Default, Clone)]
+pub struct ManifestReport {
+    pub errors: Vec<ManifestError>,
+}
+
+impl ManifestReport {
+    /// collect loads every registry from disk and gathers what went wrong
+    pub fn collect() -> Self {
+        load_from_disk();
+        locale::load();
+        Self::current()
+    }
+
+    /// current is every error reported so far
+    pub fn current() -> Self {
+        Self {
+            errors: LoadErrors.lock().unwrap().clone(),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+pub const MANIFEST_PATH: &str = "data/manifest.yml";
+
+/// locate finds the line and column of `key: value` in a file, the parsers lose track of
+/// where an entry was once it's been parsed. RON's `"key": "value",` is found as well.
+fn locate(text: &str, key: &str, value: &str) -> Option<(usize, usize)> {
+    let quotes = |c: char| c == '"' || c == '\'';
+    text.lines().enumerate().find_map(|(i, l)| {
+        let col = l.find(key)?;
+        let rest = l[col + key.len()..].trim_start_matches(quotes).trim_start().strip_prefix(':')?;
+        if rest.trim().trim_end_matches(',').trim_matches(quotes) == value {
+            return Some((i + 1, col + 1));
+        }
+        None
+    })
+}
+
+/// entry_id reads the id of an entry before it's parsed, for entries that can't be parsed
+/// on their own and for error messages
+fn entry_id<T: ManifestData>(v: &serde_yaml::Value) -> Option<String> {
+    v.get(T::ID_KEY).and_then(|id| id.as_str()).map(|id| id.to_string())
+}
+
+// the base game is always loaded first, mods can't load before it
+pub const BASE_CONTENT: &str = "base";
+// every directory in here with a manifest.yml is a mod
+pub const MODS_DIR: &str = "mods";
+
+/// ModManifest is the manifest of a content root, the base game or a mod. Roots load by
+/// load_order, then by name, and always after the roots they depend on.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ModManifest {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub load_order: i32,
+    #[serde(default)]
+    pub depends: Vec<String>,
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// ContentRoot is a loaded ModManifest, entry paths are relative to dir
+#[derive(Clone)]
+pub struct ContentRoot {
+    pub name: String,
+    pub dir: std::path::PathBuf,
+    pub manifest: String,
+    pub load_order: i32,
+    pub depends: Vec<String>,
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl ContentRoot {
+    fn read(name: &str, dir: std::path::PathBuf, manifest: String) -> Result<Self, ManifestError> {
+        let text = std::fs::read_to_string(&manifest)
+            .map_err(|e| ManifestError::new(manifest.as_str(), e.to_string()))?;
+        let mf = serde_yaml::from_str::<ModManifest>(&text)
+            .map_err(|e| ManifestError::from_yaml(&manifest, &e))?;
+        Ok(Self {
+            name: if mf.name.is_empty() { name.to_string() } else { mf.name },
+            dir,
+            manifest,
+            load_order: mf.load_order,
+            depends: mf.depends,
+            entries: mf.entries,
+        })
+    }
+
+    /// path is where an entry's file is on disk
+    pub fn path(&self, e: &ManifestEntry) -> String {
+        self.dir.join(&e.path).to_string_lossy().to_string()
+    }
+}
+
+/// ContentFile is one data file and the root it came from
+#[derive(Clone)]
+pub struct ContentFile {
+    pub root: String,
+    pub kind: ManifestType,
+    pub path: String,
+}
+
+lazy_static! {
+    // the content roots as they were last read, see content_roots
+    static ref Roots: RwLock<Option<Vec<ContentRoot>>> = Default::default();
+}
+
+/// content_roots are the base game and every mod, in the order they load. The manifests
+/// are only read the first time, see reload_content_roots.
+pub fn content_roots() -> Vec<ContentRoot> {
+    if let Some(roots) = Roots.read().unwrap().as_ref() {
+        return roots.clone();
+    }
+    let mut cached = Roots.write().unwrap();
+    // another thread may have read them while this one waited
+    cached.get_or_insert_with(read_content_roots).clone()
+}
+
+/// reload_content_roots reads every manifest again, for when one changed on disk
+pub(crate) fn reload_content_roots() -> Vec<ContentRoot> {
+    let roots = read_content_roots();
+    *Roots.write().unwrap() = Some(roots.clone());
+    roots
+}
+
+/// read_content_roots reads the base game and every mod, in the order they load
+fn read_content_roots() -> Vec<ContentRoot> {
+    let base = ContentRoot::read(BASE_CONTENT, Default::default(), MANIFEST_PATH.to_string())
+        .map(|mut base| {
+            base.name = BASE_CONTENT.to_string();
+            base
+        })
+        .map_err(report)
+        .ok();
+    let mods: Vec<ContentRoot> = std::fs::read_dir(MODS_DIR).into_iter()
+        .flatten()
+        .flatten()
+        .filter(|d| d.path().is_dir())
+        .filter_map(|d| {
+            let dir = d.path();
+            let manifest = dir.join("manifest.yml");
+            if !manifest.exists() {
+                return None;
+            }
+            let name = d.file_name().to_string_lossy().to_string();
+            ContentRoot::read(&name, dir, manifest.to_string_lossy().to_string())
+                .map_err(report)
+                .ok()
+        })
+        .collect();
+    order_roots(base, mods)
+}
+
+/// order_roots puts the mods in the order they load, after the base game. Mods with a
+/// missing dependency, in a dependency cycle, or with a name that's already taken, are
+/// reported and left out.
+fn order_roots(base: Option<ContentRoot>, mut mods: Vec<ContentRoot>) -> Vec<ContentRoot> {
+    let mut roots: Vec<ContentRoot> = base.into_iter().collect();
+    // depends names mods, so a name has to point at one root. The first directory to
+    // claim a name keeps it.
+    mods.sort_by(|a, b| a.dir.cmp(&b.dir));
+    let mut taken: Vec<(String, String)> = roots.iter().map(|r| (r.name.clone(), r.manifest.clone())).collect();
+    mods.retain(|m| {
+        if let Some((_, by)) = taken.iter().find(|(n, _)| *n == m.name) {
+            report(ManifestError::new(m.manifest.as_str(), format!("the name '{}' is already used by {}", m.name, by)));
+            return false;
+        }
+        taken.push((m.name.clone(), m.manifest.clone()));
+        true
+    });
+    mods.sort_by(|a, b| a.load_order.cmp(&b.load_order).then(a.name.cmp(&b.name)));
+
+    let known: Vec<String> = roots.iter().chain(mods.iter()).map(|r| r.name.clone()).collect();
+    mods.retain(|m| {
+        let missing: Vec<&String> = m.depends.iter().filter(|d| !known.contains(d)).collect();
+        for d in missing.iter() {
+            report(ManifestError::new(m.manifest.as_str(), format!("depends on missing mod '{}'", d)));
+        }
+        missing.is_empty()
+    });
+    // take the first mod in order whose dependencies are all loaded, until none are left
+    while !mods.is_empty() {
+        let ready = mods.iter().position(|m| {
+            m.depends.iter().all(|d| roots.iter().any(|r: &ContentRoot| &r.name == d))
+        });
+        match ready {
+            Some(i) => roots.push(mods.remove(i)),
+            None => {
+                for m in mods.drain(..) {
+                    report(ManifestError::new(m.manifest.as_str(), "is part of a dependency cycle"));
+                }
+            }
         }
     }
+    roots
+}
+
+/// content_files are all data files of every root, in load order
+pub fn content_files() -> Vec<ContentFile> {
+    content_roots().iter()
+        .flat_map(|r| r.entries.iter().map(move |e| ContentFile {
+            root: r.name.clone(),
+            kind: e.kind.clone(),
+            path: r.path(e),
+        }))
+        .collect()
+}
+
+/// Source is one parsed data file
+pub struct Source<'a> {
+    pub root: &'a str,
+    pub path: &'a str,
+    // kept to point errors at the line they're on
+    pub text: &'a str,
+    pub entries: &'a [serde_yaml::Value],
+}
+
+/// parse_entries reads the entries of a data file as loose values, they're only checked
+/// against their type once every file of the kind is in, see build_registry
+pub fn parse_entries(path: &str, text: &str) -> Result<Vec<serde_yaml::Value>, ManifestError> {
+    format::parse::<ManifestFile<serde_yaml::Value>>(path, text).map(|f| f.entries)
+}
+
+/// read_sources reads and parses every file of the kind from disk. Files that can't be read
+/// are reported and skipped.
+fn read_sources(kind: &ManifestType) -> Vec<(ContentFile, String, Vec<serde_yaml::Value>)> {
+    content_files().into_iter()
+        .filter(|f| f.kind == *kind)
+        .filter_map(|f| {
+            let read = std::fs::read_to_string(&f.path)
+                .map_err(|e| ManifestError::new(f.path.as_str(), e.to_string()))
+                .and_then(|text| parse_entries(&f.path, &text).map(|entries| (text, entries)));
+            match read {
+                Ok((text, entries)) => Some((f, text, entries)),
+                Err(e) => {
+                    report(e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// load_from_disk fills every registry straight from the files, for the command line
+/// tools. The game loads them through the asset server instead, see assets.
+pub fn load_from_disk() {
+    for kind in ManifestType::load_order() {
+        let files = read_sources(&kind);
+        let sources: Vec<Source> = files.iter()
+            .map(|(f, text, entries)| Source { root: &f.root, path: &f.path, text, entries })
+            .collect();
+        rebuild(&kind, &sources);
+    }
+}
+
+/// rebuild swaps the registry of the kind for one built from the sources, forgetting the
+/// errors the files had before. Returns false for the manifest itself, which isn't a
+/// registry.
+pub(crate) fn rebuild(kind: &ManifestType, sources: &[Source]) -> bool {
+    for s in sources {
+        clear_reports(s.path);
+    }
+    match kind {
+        ManifestType::BuildingData => Buildings.replace(check_costs(build_registry(sources))),
+        ManifestType::LivingData => Entities.replace(build_registry(sources)),
+        ManifestType::RaceData => Races.replace(build_registry(sources)),
+        ManifestType::MechPartData => MechParts.replace(build_registry(sources)),
+        ManifestType::ClassData => Classes.replace(build_registry(sources)),
+        ManifestType::CoreData => Cores.replace(build_registry(sources)),
+        ManifestType::ItemData => Items.replace(build_registry(sources)),
+        ManifestType::CategoryData => Categories.replace(build_registry(sources)),
+        ManifestType::MaterialData => Materials.replace(build_registry(sources)),
+        ManifestType::RecipeData => Recipes.replace(build_registry(sources)),
+        ManifestType::TraitData => Traits.replace(build_registry(sources)),
+        ManifestType::SkillCapabilityData => SkillCapabilities.replace(build_registry(sources)),
+        ManifestType::ManifestData => return false,
+    }
+    true
+}
+
+lazy_static! {
+    // the file and position every entry was last loaded from
+    static ref EntryPaths: Mutex<StableHashMap<(ManifestType, String), (String, Option<(usize, usize)>)>> = Default::default();
+    // which roots added or changed each entry, by manifest type and id
+    static ref Contributions: Mutex<StableHashMap<ManifestType, Vec<(String, Vec<String>)>>> = Default::default();
+}
+
+/// contributions lists which content roots added or changed each entry of the type,
+/// in load order
+pub fn contributions(kind: &ManifestType) -> Vec<(String, Vec<String>)> {
+    Contributions.lock().unwrap().get(kind).cloned().unwrap_or_default()
 }
 
-fn load_manifest_data<'a, T>() -> StableHashMap<String, T>
-    where
-        T: ManifestData + Default + Clone + Serialize + DeserializeOwned + 'static {
-    let mf = "data/manifest.yml";
-    let f = std::fs::File::open(mf);
-    if let Err(e) = f {
-        panic!("bad manifest file: '{}'", e);
+/// entry_error is an error about a loaded entry, pointing at where it was loaded from.
+/// Entries that never loaded point at the first file of their kind, or at the manifest
+/// when there's none.
+pub fn entry_error<M: Into<String>>(kind: &ManifestType, id: &str, message: M) -> ManifestError {
+    let found = EntryPaths.lock().unwrap().get(&(kind.clone(), id.to_string())).cloned();
+    let err = match found {
+        Some((path, Some((l, c)))) => ManifestError::new(path, message).at(l, c),
+        Some((path, None)) => ManifestError::new(path, message),
+        None => {
+            let path = content_files().into_iter()
+                .find(|f| f.kind == *kind)
+                .map(|f| f.path)
+                .unwrap_or_else(|| MANIFEST_PATH.to_string());
+            ManifestError::new(path, message)
+        }
+    };
+    err.entry(id)
+}
+
+/// merge lays the patch over the value, mappings are merged key by key and anything
+/// else is replaced
+fn merge(value: &mut serde_yaml::Value, patch: serde_yaml::Value) {
+    match (value, patch) {
+        (serde_yaml::Value::Mapping(v), serde_yaml::Value::Mapping(p)) => {
+            for (k, pv) in p.into_iter() {
+                match v.get_mut(&k) {
+                    Some(vv) => merge(vv, pv),
+                    None => {
+                        v.insert(k, pv);
+                    }
+                }
+            }
+        }
+        (v, p) => *v = p,
     }
-    let ds = serde_yaml::from_reader::<File, ManifestFile<ManifestEntry>>(f.unwrap());
+}
+
+// an entry with this key changes some fields of the entry with that id instead of
+// adding a new one
+const PATCH_KEY: &str = "patch";
+// an entry with this key starts from the entry with that id and only lists what's
+// different, abstract entries are only there to be extended and never load
+const EXTENDS_KEY: &str = "extends";
+const ABSTRACT_KEY: &str = "abstract";
+
+struct Merged {
+    value: serde_yaml::Value,
+    path: String,
+    loc: Option<(usize, usize)>,
+    roots: Vec<String>,
+    extends: Option<String>,
+    is_abstract: bool,
+}
+
+/// take_key removes a key from an entry
+fn take_key(v: &mut serde_yaml::Value, key: &str) -> Option<serde_yaml::Value> {
+    v.as_mapping_mut().and_then(|m| m.remove(&serde_yaml::Value::from(key)))
+}
 
-    if let Err(e) = ds {
-        panic!("bad manifest file contents: '{}'", e);
+/// resolve lays an entry over the entries it extends, the furthest ancestor first.
+/// chain is the ids already being resolved, to find cycles.
+fn resolve(id: &str, merged: &StableHashMap<String, Merged>, chain: &mut Vec<String>) -> Result<serde_yaml::Value, String> {
+    let m = match merged.get(id) {
+        Some(m) => m,
+        None => return Err(format!("extends '{}', which doesn't exist", id)),
+    };
+    chain.push(id.to_string());
+    let parent = match &m.extends {
+        None => return Ok(m.value.clone()),
+        Some(p) if chain.contains(p) => {
+            return Err(format!("extends itself: {} -> {}", chain.join(" -> "), p));
+        }
+        Some(p) => p,
+    };
+    let mut value = resolve(parent, merged, chain)?;
+    merge(&mut value, m.value.clone());
+    Ok(value)
+}
+
+/// build_registry loads the entries of every source in load order. A root can add
+/// entries, replace an entry of an earlier root by using its id, or patch some of its
+/// fields. Entries can extend any other entry of the same type, from any file or root, and
+/// are resolved once everything is loaded.
+pub(crate) fn build_registry<T>(sources: &[Source]) -> StableHashMap<String, T>
+    where T: ManifestData + DeserializeOwned {
+    let mut merged: StableHashMap<String, Merged> = Default::default();
+    let mut order: Vec<String> = vec![];
+    for s in sources.iter() {
+        let (root, path, text) = (&s.root, &s.path, s.text);
+        // the entries are loose values so one bad entry doesn't hide the others, RON
+        // entries end up as the same values so patches work across formats
+        for mut v in s.entries.iter().cloned() {
+            let found = entry_id::<T>(&v);
+            let loc = found.as_ref().and_then(|id| locate(text, T::ID_KEY, id));
+            let with_loc = |e: ManifestError| match loc {
+                Some((l, c)) => e.at(l, c),
+                None => e,
+            };
+
+            let extends = match take_key(&mut v, EXTENDS_KEY) {
+                Some(serde_yaml::Value::String(p)) => Some(p),
+                Some(_) => {
+                    report(with_loc(ManifestError::new(*path, "extends needs the id of an entry")));
+                    continue;
+                }
+                None => None,
+            };
+            let is_abstract = match take_key(&mut v, ABSTRACT_KEY) {
+                Some(serde_yaml::Value::Bool(a)) => Some(a),
+                Some(_) => {
+                    report(with_loc(ManifestError::new(*path, "abstract needs to be true or false")));
+                    continue;
+                }
+                None => None,
+            };
+
+            if let Some(patch) = take_key(&mut v, PATCH_KEY) {
+                let id = patch.as_str().unwrap_or_default().to_string();
+                let loc = locate(text, PATCH_KEY, &id).or(loc);
+                match merged.get_mut(&id) {
+                    Some(m) => {
+                        merge(&mut m.value, v);
+                        m.path = path.to_string();
+                        m.loc = loc;
+                        m.roots.push(root.to_string());
+                        if extends.is_some() {
+                            m.extends = extends;
+                        }
+                        // a patch can hide an entry or bring an abstract one in
+                        if let Some(a) = is_abstract {
+                            m.is_abstract = a;
+                        }
+                    }
+                    None => report(with_loc(ManifestError::new(*path, "patches an entry that doesn't exist")).entry(id)),
+                }
+                continue;
+            }
+
+            // entries that extend another or are abstract are usually incomplete, they're
+            // checked once resolved
+            let is_abstract = is_abstract.unwrap_or(false);
+            let id = if extends.is_some() || is_abstract {
+                match &found {
+                    Some(id) => id.clone(),
+                    None => {
+                        report(with_loc(ManifestError::new(*path, "an entry that extends another or is abstract needs an id")));
+                        continue;
+                    }
+                }
+            } else {
+                match serde_yaml::from_value::<T>(v.clone()) {
+                    Ok(bd) => bd.id(),
+                    Err(e) => {
+                        let mut err = with_loc(ManifestError::new(*path, e.to_string()));
+                        if let Some(id) = found {
+                            err = err.entry(id);
+                        }
+                        report(err);
+                        continue;
+                    }
+                }
+            };
+            let mut roots = vec![root.to_string()];
+            if let Some(old) = merged.get(&id) {
+                if old.roots.last().map(|r| r == root).unwrap_or(false) {
+                    report(with_loc(ManifestError::new(*path, format!("duplicate {:?} id", T::kind())).entry(id)));
+                    continue;
+                }
+                // a later root replaces the entry, but it's still listed as contributing
+                roots = old.roots.clone();
+                roots.push(root.to_string());
+            } else {
+                order.push(id.clone());
+            }
+            merged.insert(id, Merged { value: v, path: path.to_string(), loc, roots, extends, is_abstract });
+        }
     }
+
     let mut bdm: StableHashMap<String, T> = Default::default();
-    ds.unwrap().entries.iter().for_each(|f| {
-        if f.kind == T::kind() {
-            let path = f.path.clone();
-            let file = std::fs::File::open(path.as_str());
-            if let Err(e) = file {
-                panic!("bad manifest file: '{}': '{}'", path, e);
+    let mut contributed = vec![];
+    for id in order {
+        let m = match merged.get(&id) {
+            Some(m) if !m.is_abstract => m,
+            _ => continue,
+        };
+        let err = |message: String| {
+            let err = ManifestError::new(m.path.as_str(), message).entry(id.as_str());
+            match m.loc {
+                Some((l, c)) => err.at(l, c),
+                None => err,
+            }
+        };
+        let value = match resolve(&id, &merged, &mut vec![]) {
+            Ok(v) => v,
+            Err(msg) => {
+                report(err(msg));
+                continue;
             }
-            let ds = serde_yaml::from_reader::<File, ManifestFile::<T>>(file.unwrap());
-            if let Err(e) = ds {
-                panic!("bad manifest file contents: '{}': '{}'", path, e);
+        };
+        // patches and inherited fields can still break an entry
+        match serde_yaml::from_value::<T>(value) {
+            Ok(bd) => {
+                let key = bd.id();
+                EntryPaths.lock().unwrap().insert((T::kind(), key.clone()), (m.path.clone(), m.loc));
+                bdm.insert(key.clone(), bd);
+                contributed.push((key, m.roots.clone()));
             }
-            let fs = ds.unwrap();
-            fs.entries.iter().for_each(|bd| {
-                bdm.insert(bd.id(), bd.clone());
-            })
+            Err(e) => report(err(e.to_string())),
         }
-    });
+    }
+    Contributions.lock().unwrap().insert(T::kind(), contributed);
     bdm
 }
 
-#[derive(Clone, Serialize, Deserialize, Eq, PartialEq)]
+/// Entries are the loaded entries of one manifest type at some point, by id
+pub type Entries<T> = Arc<StableHashMap<String, Arc<T>>>;
+
+/// Registry holds the loaded entries of one manifest type. It's swapped for a fresh load
+/// whenever a file of the type changes. Entries are shared, so anything still holding one
+/// from before keeps it alive until it lets go, and the old load is dropped after that.
+///
+/// Every id is also interned into a small integer, see ids. Numbers are handed out in id
+/// order as entries load and are never reused, so a reload can't make one point at a
+/// different entry.
+pub struct Registry<T: 'static> {
+    current: RwLock<Entries<T>>,
+    interned: RwLock<Interned>,
+}
+
+#[derive(Default)]
+struct Interned {
+    numbers: StableHashMap<&'static str, u32>,
+    names: Vec<&'static str>,
+}
+
+impl Interned {
+    // the names are leaked, there's one per id ever loaded and they're never freed
+    fn intern<'a>(&mut self, ids: impl Iterator<Item = &'a String>) {
+        let mut ids: Vec<&String> = ids.filter(|id| !self.numbers.contains_key(id.as_str())).collect();
+        ids.sort();
+        for id in ids {
+            let name: &'static str = Box::leak(id.clone().into_boxed_str());
+            self.numbers.insert(name, self.names.len() as u32);
+            self.names.push(name);
+        }
+    }
+}
+
+impl<T: 'static> Default for Registry<T> {
+    fn default() -> Self {
+        Self {
+            current: RwLock::new(Default::default()),
+            interned: Default::default(),
+        }
+    }
+}
+
+impl<T: 'static> Registry<T> {
+    pub fn replace(&self, entries: StableHashMap<String, T>) {
+        self.interned.write().unwrap().intern(entries.keys());
+        let entries = entries.into_iter().map(|(k, v)| (k, Arc::new(v))).collect();
+        *self.current.write().unwrap() = Arc::new(entries);
+    }
+
+    /// entries are all entries as they are now, later reloads don't change them
+    pub fn entries(&self) -> Entries<T> {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<T>> {
+        self.current.read().unwrap().get(id).cloned()
+    }
+
+    pub fn contains_key(&self, id: &str) -> bool {
+        self.current.read().unwrap().contains_key(id)
+    }
+
+    pub fn values(&self) -> Vec<Arc<T>> {
+        self.current.read().unwrap().values().cloned().collect()
+    }
+
+    /// number is the interned number of a loaded entry
+    pub fn number(&self, id: &str) -> Option<u32> {
+        if !self.contains_key(id) {
+            return None;
+        }
+        self.interned.read().unwrap().numbers.get(id).copied()
+    }
+
+    /// name is the id an interned number stands for, even if the entry was since removed
+    pub fn name(&self, number: u32) -> Option<&'static str> {
+        self.interned.read().unwrap().names.get(number as usize).copied()
+    }
+
+    /// by_number is the loaded entry an interned number stands for
+    pub fn by_number(&self, number: u32) -> Option<Arc<T>> {
+        self.get(self.name(number)?)
+    }
+
+    /// names are all ids interned so far, indexed by number. Saves keep this table so
+    /// they can be loaded against different data.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.interned.read().unwrap().names.clone()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub enum ManifestType {
     ManifestData,
     BuildingData,
     LivingData,
     RaceData,
+    MechPartData,
+    ClassData,
+    CoreData,
+    ItemData,
+    CategoryData,
+    MaterialData,
+    RecipeData,
+    TraitData,
+    SkillCapabilityData,
+}
+
+impl ManifestType {
+    /// ALL are the kinds of data files, ManifestData is the manifest itself so it's left out
+    pub const ALL: [ManifestType; 12] = [
+        ManifestType::BuildingData,
+        ManifestType::LivingData,
+        ManifestType::RaceData,
+        ManifestType::MechPartData,
+        ManifestType::ClassData,
+        ManifestType::CoreData,
+        ManifestType::ItemData,
+        ManifestType::CategoryData,
+        ManifestType::MaterialData,
+        ManifestType::RecipeData,
+        ManifestType::TraitData,
+        ManifestType::SkillCapabilityData,
+    ];
+
+    /// load_order is ALL in the order registries are built. Building costs are read as
+    /// item ids, so buildings come last.
+    pub fn load_order() -> Vec<ManifestType> {
+        let mut kinds = Self::ALL.to_vec();
+        kinds.sort_by_key(|k| *k == ManifestType::BuildingData);
+        kinds
+    }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ManifestEntry {
-    path: String,
-    kind: ManifestType,
+    /// the data file, .ron files are read as RON and anything else as YAML
+    pub path: String,
+    pub kind: ManifestType,
 }
 
 impl ManifestData for ManifestEntry {
+    const ID_KEY: &'static str = "path";
+
     fn kind() -> ManifestType {
         ManifestType::ManifestData
     }
@@ -164,26 +989,27 @@ impl Default for ManifestEntry {
     }
 }
 
-#[derive(Default, Clone, Serialize, Deserialize)]
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(bound = "T: Default + Clone + Serialize + for<'a> Deserialize<'a>")]
+#[schemars(bound = "T: JsonSchema")]
 pub struct ManifestFile<T: Default + Clone + Serialize + for<'a> Deserialize<'a>> {
     entries: Vec<T>,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum Properties {
     Usable,
     Toggleable,
     Powered
 }
 
-#[derive(Default, Clone, Serialize, Deserialize)]
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Capability {
     pub name: String,
     pub level: i32,
 }
 
-#[derive(Default, Clone, Serialize, Deserialize)]
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Effects {
     pub name: String,
     pub value: i32,
@@ -195,11 +1021,15 @@ pub trait HasAsset {
 }
 
 pub trait ManifestData {
+    /// ID_KEY is the field id() is read from, so entries can be told apart before they
+    /// parse
+    const ID_KEY: &'static str = "id";
+
     fn kind() -> ManifestType;
     fn id(&self) -> String;
 }
 
-#[derive(Default, Clone, Serialize, Deserialize)]
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct BuildingData {
     pub name: String,
     pub id: String,
@@ -208,14 +1038,21 @@ pub struct BuildingData {
     pub base_time: i32,
     pub power_used: Option<i32>,
     pub cost: Option<Vec<Cost>>,
+    #[schemars(skip)]
     pub handle: Option<HandleId>,
 
-    // the base capabilities this building has
+    /// the base capabilities this building has
     pub caps: Option<Vec<Capability>>,
-    // environmental effects
+    /// environmental effects
     pub effects: Option<Vec<Effects>>,
-    // what can be done with the building
+    /// what can be done with the building
     pub properties: Option<Vec<Properties>>,
+    /// the weight of items the building can store
+    pub storage: Option<f32>,
+    /// the comfort sleeping in the building restores per second, only beds have it
+    pub bed: Option<f32>,
+    /// how nice the building is to look at, negative is ugly
+    pub beauty: Option<i32>,
 
     pub tags: Option<Vec<String>>
 }
@@ -229,6 +1066,40 @@ impl ManifestData for BuildingData {
         self.id.clone()
     }
 }
+impl BuildingData {
+    /// cap_level is the level of the named capability, if the building has it
+    pub fn cap_level(&self, name: &str) -> Option<i32> {
+        self.caps.iter().flatten().find(|c| c.name == name).map(|c| c.level)
+    }
+
+    pub fn has_property(&self, p: Properties) -> bool {
+        self.properties.iter().flatten().any(|pp| *pp == p)
+    }
+
+    pub fn costs(&self) -> &[Cost] {
+        self.cost.as_deref().unwrap_or(&[])
+    }
+}
+
+/// check_costs makes sure every building cost is for at least one item. Costs naming an
+/// item that doesn't exist already fail to load.
+pub(crate) fn check_costs(bdm: StableHashMap<String, BuildingData>) -> StableHashMap<String, BuildingData> {
+    for bd in bdm.values() {
+        cost_errors(bd).into_iter().for_each(report);
+    }
+    bdm
+}
+
+pub(crate) fn cost_errors(bd: &BuildingData) -> Vec<ManifestError> {
+    let mut errs = vec![];
+    for c in bd.costs() {
+        if c.value <= 0 {
+            errs.push(entry_error(&ManifestType::BuildingData, &bd.id, format!("costs {} of '{}'", c.value, c.name)));
+        }
+    }
+    errs
+}
+
 impl HasAsset for BuildingData {
     fn path(&self) -> String {
         self.path.clone()
@@ -236,17 +1107,432 @@ impl HasAsset for BuildingData {
 }
 
 
-#[derive(Default, Clone, Serialize, Deserialize)]
+/// MaterialData is something things can be made of. The property ranges are rolled every
+/// time something is made from it.
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MaterialData {
     pub name: String,
     pub id: String,
+    #[serde(default)]
     pub description: String,
-    pub hp_mod: f64,
-    pub weight_mod: f64,
+    #[serde(default)]
+    pub properties: Vec<MaterialProperty>,
+}
+
+impl ManifestData for MaterialData {
+    fn kind() -> ManifestType {
+        ManifestType::MaterialData
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum MaterialAttr {
+    Damage,
+    #[serde(alias = "Durabillity")]
+    Durability,
+    Weight,
 }
 
-#[derive(Default, Clone, Serialize, Deserialize)]
+/// MaterialProperty is a percent change to a stat, rolled between the two values
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MaterialProperty {
+    pub attr: MaterialAttr,
+    pub value: [i32; 2],
+}
+
+/// Cost is how many of an item a building takes to build
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Cost {
-    pub name: String,
+    pub name: ids::ItemId,
     pub value: i32,
 }
+
+/// MechPartData is a part that can be fitted into a slot of a mech
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MechPartData {
+    pub name: String,
+    pub id: String,
+    pub description: String,
+    pub slot: MechSlot,
+    pub stats: MechStats,
+}
+
+impl ManifestData for MechPartData {
+    fn kind() -> ManifestType {
+        ManifestType::MechPartData
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+}
+
+/// MechStats are summed over all parts of a mech
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct MechStats {
+    pub hp: f32,
+    pub armor: f32,
+    pub speed: f32,
+    /// power generated, negative values are consumed
+    pub power: f32,
+    pub damage: f32,
+}
+
+impl Add for MechStats {
+    type Output = MechStats;
+
+    fn add(self, o: Self) -> Self::Output {
+        MechStats{
+            hp: self.hp + o.hp,
+            armor: self.armor + o.armor,
+            speed: self.speed + o.speed,
+            power: self.power + o.power,
+            damage: self.damage + o.damage,
+        }
+    }
+}
+
+impl AddAssign for MechStats {
+    fn add_assign(&mut self, o: Self) {
+        *self = *self + o;
+    }
+}
+
+/// ClassData is the progression of a class, the skills it learns from and its ranks
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ClassData {
+    pub class: Class,
+    pub description: String,
+    /// skills that give full experience
+    pub skills: Vec<String>,
+    pub ranks: Vec<RankData>,
+}
+
+impl ClassData {
+    pub fn key(c: Class) -> String {
+        format!("{:?}", c)
+    }
+}
+
+impl ManifestData for ClassData {
+    const ID_KEY: &'static str = "class";
+
+    fn kind() -> ManifestType {
+        ManifestType::ClassData
+    }
+
+    fn id(&self) -> String {
+        ClassData::key(self.class)
+    }
+}
+
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RankData {
+    pub rank: ExperienceModifier,
+    /// total experience needed to reach the rank
+    pub xp: f32,
+    #[serde(default)]
+    pub bonuses: Vec<entity::Modifier>,
+}
+
+/// CoreData lists the abilities granted by a core modifier
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CoreData {
+    pub core: Modifier,
+    pub description: String,
+    pub abilities: Vec<Ability>,
+}
+
+impl ManifestData for CoreData {
+    const ID_KEY: &'static str = "core";
+
+    fn kind() -> ManifestType {
+        ManifestType::CoreData
+    }
+
+    fn id(&self) -> String {
+        format!("{:?}", self.core)
+    }
+}
+
+/// ItemData is the definition of an item, everything about an item that isn't rolled
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ItemData {
+    pub name: String,
+    pub id: String,
+    pub description: String,
+    pub category: Option<String>,
+    #[serde(default = "default_stack_size")]
+    pub stack_size: u32,
+    pub weight: f32,
+    /// items that can be eaten restore this much hunger
+    pub nutrition: Option<f32>,
+    pub damage: Option<f32>,
+    pub durability: Option<f32>,
+    /// how nice the item is to look at, negative is ugly
+    pub beauty: Option<i32>,
+    #[serde(default)]
+    pub traits: Vec<item::Trait>,
+    /// worn gear
+    pub armor: Option<f32>,
+    /// percent change to move speed
+    pub move_speed: Option<i32>,
+    #[serde(default)]
+    pub modifiers: Vec<entity::Modifier>,
+    /// what the item breaks down into, derived from the recipe that makes it if not set
+    pub salvage: Option<Vec<RecipeOutput>>,
+}
+
+fn default_stack_size() -> u32 {
+    1
+}
+
+impl ManifestData for ItemData {
+    fn kind() -> ManifestType {
+        ManifestType::ItemData
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+}
+
+/// CategoryData is a node of the item category tree. Either `parent` or `parents` may be
+/// used, they are merged.
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CategoryData {
+    pub name: String,
+    pub description: String,
+    pub parent: Option<String>,
+    pub parents: Option<Vec<String>>,
+    /// the slot items of this category are worn in
+    pub target: Option<OrganicSlot>,
+}
+
+impl CategoryData {
+    pub fn parents(&self) -> Vec<&String> {
+        self.parent.iter()
+            .chain(self.parents.iter().flatten())
+            .collect()
+    }
+}
+
+impl ManifestData for CategoryData {
+    const ID_KEY: &'static str = "name";
+
+    fn kind() -> ManifestType {
+        ManifestType::CategoryData
+    }
+
+    fn id(&self) -> String {
+        self.name.clone()
+    }
+}
+
+/// RecipeData turns inputs into outputs at a workstation with the right capability
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecipeData {
+    pub name: String,
+    pub id: String,
+    pub inputs: Vec<RecipeInput>,
+    pub outputs: Vec<RecipeOutput>,
+    /// seconds of work for a worker with a skill of 1
+    pub work_time: f32,
+    pub capability: Capability,
+    #[serde(default = "default_recipe_skill")]
+    pub skill: String,
+    /// workers below this skill can't make it
+    #[serde(default)]
+    pub min_skill: f32,
+    /// the material the outputs are made of
+    pub material: Option<String>,
+}
+
+fn default_recipe_skill() -> String {
+    "fabrication".to_string()
+}
+
+impl ManifestData for RecipeData {
+    fn kind() -> ManifestType {
+        ManifestType::RecipeData
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+}
+
+/// SkillCapabilityData maps a skill level to the level of a capability it gives, the
+/// keys of the mapping are skill levels
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SkillCapabilityData {
+    pub input_skill: String,
+    pub target_cap: String,
+    #[serde(default)]
+    pub mapping: Vec<std::collections::BTreeMap<i32, i32>>,
+}
+
+impl ManifestData for SkillCapabilityData {
+    const ID_KEY: &'static str = "target_cap";
+
+    fn kind() -> ManifestType {
+        ManifestType::SkillCapabilityData
+    }
+
+    fn id(&self) -> String {
+        self.target_cap.clone()
+    }
+}
+
+/// RecipeInput is either a specific item, or any item of a category
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecipeInput {
+    pub item: Option<String>,
+    pub category: Option<String>,
+    pub count: u32,
+}
+
+#[derive(Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecipeOutput {
+    pub item: String,
+    pub count: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(files: &[(&str, &str, &str)]) -> StableHashMap<String, MaterialData> {
+        let parsed: Vec<Vec<serde_yaml::Value>> = files.iter()
+            .map(|(_, path, text)| parse_entries(path, text).unwrap())
+            .collect();
+        let sources: Vec<Source> = files.iter().zip(parsed.iter())
+            .map(|((root, path, text), entries)| Source { root, path, text, entries })
+            .collect();
+        build_registry(&sources)
+    }
+
+    fn errors(path: &str) -> Vec<String> {
+        ManifestReport::current().errors.into_iter()
+            .filter(|e| e.path == path)
+            .map(|e| e.message)
+            .collect()
+    }
+
+    #[test]
+    fn later_roots_replace_and_patch_entries() {
+        let m = build(&[
+            ("base", "test/replace/base.yml", "entries:\n  - { id: steel, name: Steel }\n  - { id: oak, name: Oak }\n"),
+            ("mod", "test/replace/mod.yml", "entries:\n  - { id: steel, name: Hard Steel }\n  - { patch: oak, description: Sturdy }\n"),
+        ]);
+        assert_eq!(m["steel"].name, "Hard Steel");
+        assert_eq!((m["oak"].name.as_str(), m["oak"].description.as_str()), ("Oak", "Sturdy"));
+    }
+
+    #[test]
+    fn extends_inherits_and_abstract_entries_are_left_out() {
+        let m = build(&[
+            ("base", "test/extends/base.yml", "entries:
+  - id: metal
+    abstract: true
+    description: Shiny
+    properties: [{ attr: Weight, value: [10, 20] }]
+  - { id: steel, name: Steel, extends: metal }
+  - { id: tin, name: Tin, extends: metal, description: Soft }
+"),
+        ]);
+        assert!(!m.contains_key("metal"));
+        assert_eq!(m["steel"].description, "Shiny");
+        assert_eq!(m["steel"].properties[0].value, [10, 20]);
+        assert_eq!(m["tin"].description, "Soft");
+    }
+
+    #[test]
+    fn patches_can_make_entries_abstract() {
+        let m = build(&[
+            ("base", "test/abstract/base.yml", "entries:\n  - { id: steel, name: Steel }\n  - { id: oak, name: Oak, abstract: true }\n"),
+            ("mod", "test/abstract/mod.yml", "entries:\n  - { patch: steel, abstract: true }\n  - { patch: oak, abstract: false }\n"),
+        ]);
+        assert!(!m.contains_key("steel"));
+        assert_eq!(m["oak"].name, "Oak");
+    }
+
+    #[test]
+    fn extends_cycles_are_reported() {
+        let path = "test/cycle/base.yml";
+        let m = build(&[
+            ("base", path, "entries:\n  - { id: steel, name: Steel, extends: tin }\n  - { id: tin, name: Tin, extends: steel }\n  - { id: oak, name: Oak, extends: pine }\n"),
+        ]);
+        assert!(m.is_empty());
+        let errs = errors(path);
+        assert!(errs.iter().any(|e| e.starts_with("extends itself")));
+        assert!(errs.iter().any(|e| e == "extends 'pine', which doesn't exist"));
+    }
+
+    #[test]
+    fn bad_abstract_and_duplicates_are_reported() {
+        let path = "test/duplicate/base.yml";
+        let m = build(&[
+            ("base", path, "entries:\n  - { id: steel, name: Steel }\n  - { id: steel, name: Other Steel }\n  - { id: oak, name: Oak, abstract: maybe }\n"),
+        ]);
+        assert_eq!(m["steel"].name, "Steel");
+        assert!(!m.contains_key("oak"));
+        let errs = errors(path);
+        assert!(errs.contains(&"duplicate MaterialData id".to_string()));
+        assert!(errs.contains(&"abstract needs to be true or false".to_string()));
+    }
+
+    fn root(name: &str, load_order: i32, depends: &[&str]) -> ContentRoot {
+        ContentRoot {
+            name: name.to_string(),
+            dir: std::path::Path::new(MODS_DIR).join(name),
+            manifest: format!("test/{}/manifest.yml", name),
+            load_order,
+            depends: depends.iter().map(|d| d.to_string()).collect(),
+            entries: vec![],
+        }
+    }
+
+    fn names(roots: &[ContentRoot]) -> Vec<&str> {
+        roots.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    #[test]
+    fn roots_load_by_order_then_name_after_their_dependencies() {
+        let roots = order_roots(Some(root(BASE_CONTENT, 0, &[])), vec![
+            root("zeppelins", 0, &[]),
+            root("airships", 0, &[]),
+            root("early", -5, &["late"]),
+            root("late", 5, &[]),
+        ]);
+        assert_eq!(names(&roots), vec![BASE_CONTENT, "airships", "zeppelins", "late", "early"]);
+    }
+
+    #[test]
+    fn taken_names_and_broken_dependencies_are_left_out() {
+        let mut dup = root("airships", 0, &[]);
+        dup.dir = std::path::Path::new(MODS_DIR).join("airships_copy");
+        dup.manifest = "test/airships_copy/manifest.yml".to_string();
+        let mut base_copy = root(BASE_CONTENT, 0, &[]);
+        base_copy.manifest = "test/base_copy/manifest.yml".to_string();
+        let roots = order_roots(Some(root(BASE_CONTENT, 0, &[])), vec![
+            dup,
+            root("airships", 0, &[]),
+            base_copy,
+            root("lonely", 0, &["missing"]),
+            root("chicken", 0, &["egg"]),
+            root("egg", 0, &["chicken"]),
+        ]);
+        assert_eq!(names(&roots), vec![BASE_CONTENT, "airships"]);
+        assert_eq!(roots[1].manifest, "test/airships/manifest.yml");
+        assert!(errors("test/airships_copy/manifest.yml")[0].starts_with("the name 'airships' is already used"));
+        assert!(errors("test/base_copy/manifest.yml")[0].starts_with("the name 'base' is already used"));
+        assert_eq!(errors("test/lonely/manifest.yml"), vec!["depends on missing mod 'missing'"]);
+        assert_eq!(errors("test/egg/manifest.yml"), vec!["is part of a dependency cycle"]);
+    }
+}
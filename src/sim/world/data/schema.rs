@@ -0,0 +1,110 @@
+use schemars::{
+    schema::{InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec},
+    schema_for,
+};
+use std::path::Path;
+use crate::sim::world::{data::*, entity::Trait};
+
+/// schema is the JSON Schema of a whole file of the kind
+pub fn schema(kind: &ManifestType) -> RootSchema {
+    let root = match kind {
+        ManifestType::ManifestData => return schema_for!(ModManifest),
+        ManifestType::BuildingData => schema_for!(ManifestFile<BuildingData>),
+        ManifestType::LivingData => schema_for!(ManifestFile<LivingData>),
+        ManifestType::RaceData => schema_for!(ManifestFile<RaceData>),
+        ManifestType::MechPartData => schema_for!(ManifestFile<MechPartData>),
+        ManifestType::ClassData => schema_for!(ManifestFile<ClassData>),
+        ManifestType::CoreData => schema_for!(ManifestFile<CoreData>),
+        ManifestType::ItemData => schema_for!(ManifestFile<ItemData>),
+        ManifestType::CategoryData => schema_for!(ManifestFile<CategoryData>),
+        ManifestType::MaterialData => schema_for!(ManifestFile<MaterialData>),
+        ManifestType::RecipeData => schema_for!(ManifestFile<RecipeData>),
+        ManifestType::TraitData => schema_for!(ManifestFile<Trait>),
+        ManifestType::SkillCapabilityData => schema_for!(ManifestFile<SkillCapabilityData>),
+    };
+    with_entry_keys(root)
+}
+
+/// with_entry_keys adds the keys every entry can have on top of its own fields, see
+/// build_registry. Entries that patch, extend or are abstract only list some fields, so
+/// the required ones are only required of the others.
+fn with_entry_keys(mut root: RootSchema) -> RootSchema {
+    let name = root.schema.object.as_ref()
+        .and_then(|o| o.properties.get("entries"))
+        .and_then(|s| match s {
+            Schema::Object(o) => o.array.as_ref(),
+            _ => None,
+        })
+        .and_then(|a| match &a.items {
+            Some(SingleOrVec::Single(s)) => Some(s),
+            _ => None,
+        })
+        .and_then(|s| match &**s {
+            Schema::Object(o) => o.reference.as_ref(),
+            _ => None,
+        })
+        .and_then(|r| r.rsplit('/').next())
+        .map(|n| n.to_string());
+    let entry = match name.and_then(|n| root.definitions.get_mut(&n)) {
+        Some(Schema::Object(o)) => o,
+        _ => return root,
+    };
+
+    let props = &mut entry.object().properties;
+    props.insert(PATCH_KEY.to_string(), key(InstanceType::String, "changes some fields of the entry with this id"));
+    props.insert(EXTENDS_KEY.to_string(), key(InstanceType::String, "starts from the entry with this id"));
+    props.insert(ABSTRACT_KEY.to_string(), key(InstanceType::Boolean, "only there to be extended, never loads"));
+
+    let required = std::mem::take(&mut entry.object().required);
+    if required.is_empty() {
+        return root;
+    }
+    let mut is_abstract = requires(ABSTRACT_KEY);
+    is_abstract.object().properties.insert(ABSTRACT_KEY.to_string(), Schema::Object(SchemaObject {
+        const_value: Some(serde_json::Value::Bool(true)),
+        ..Default::default()
+    }));
+    let mut partial = SchemaObject::default();
+    partial.subschemas().any_of = Some(vec![
+        requires(PATCH_KEY).into(),
+        requires(EXTENDS_KEY).into(),
+        is_abstract.into(),
+    ]);
+    let mut whole = SchemaObject::default();
+    whole.object().required = required;
+    let sub = entry.subschemas();
+    sub.if_schema = Some(Box::new(partial.into()));
+    sub.else_schema = Some(Box::new(whole.into()));
+    root
+}
+
+fn key(ty: InstanceType, description: &str) -> Schema {
+    let mut s = SchemaObject {
+        instance_type: Some(ty.into()),
+        ..Default::default()
+    };
+    s.metadata().description = Some(description.to_string());
+    s.into()
+}
+
+fn requires(k: &str) -> SchemaObject {
+    let mut s = SchemaObject::default();
+    s.object().required.insert(k.to_string());
+    s
+}
+
+/// export_schemas writes a `<kind>.schema.json` for the manifest and every kind of data
+/// file into the directory, and returns the files written
+pub fn export_schemas(dir: &Path) -> std::io::Result<Vec<String>> {
+    std::fs::create_dir_all(dir)?;
+    let mut written = vec![];
+    let kinds = std::iter::once(ManifestType::ManifestData).chain(ManifestType::ALL.iter().cloned());
+    for kind in kinds {
+        let path = dir.join(format!("{:?}.schema.json", kind));
+        let json = serde_json::to_string_pretty(&schema(&kind))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        std::fs::write(&path, json)?;
+        written.push(path.to_string_lossy().to_string());
+    }
+    Ok(written)
+}
@@ -0,0 +1,136 @@
+use crate::sim::world::{
+    body::BODY_ATTRS,
+    category::Categories,
+    data::*,
+    entity::{Modifier, ATTRIBUTES, SKILLS},
+    needs::NEED_ATTRS,
+};
+
+// the race properties the game looks for
+const RACE_PROPERTIES: [&str; 2] = ["Orderable", "Living"];
+
+/// validate checks that every id one entry uses to refer to another exists. Unknown enum
+/// values and duplicate ids are already reported while loading, see ManifestReport.
+pub fn validate() -> Vec<ManifestError> {
+    let mut errs = vec![];
+
+    for ld in Entities.values() {
+        if !Races.contains_key(&ld.race) {
+            errs.push(entry_error(&ManifestType::LivingData, &ld.id, format!("unknown race '{}'", ld.race)));
+        }
+        for d in ld.drops.iter().flat_map(|t| t.entries.iter()) {
+            check_item(&mut errs, &ManifestType::LivingData, &ld.id, &d.item);
+        }
+    }
+
+    for rd in Races.values() {
+        for p in rd.properties.iter().flatten() {
+            if !RACE_PROPERTIES.contains(&p.as_str()) {
+                errs.push(entry_error(&ManifestType::RaceData, &rd.name, format!("unknown property '{}'", p)));
+            }
+        }
+        for c in rd.cores.iter() {
+            if !Cores.contains_key(&format!("{:?}", c)) {
+                errs.push(entry_error(&ManifestType::RaceData, &rd.name, format!("unknown core '{:?}'", c)));
+            }
+        }
+    }
+
+    for bd in Buildings.values() {
+        errs.extend(cost_errors(&bd));
+        for c in bd.caps.iter().flatten() {
+            check_cap(&mut errs, &ManifestType::BuildingData, &bd.id, &c.name);
+        }
+    }
+
+    for id in Items.values() {
+        if let Some(c) = &id.category {
+            check_category(&mut errs, &ManifestType::ItemData, &id.id, c);
+        }
+        for o in id.salvage.iter().flatten() {
+            check_item(&mut errs, &ManifestType::ItemData, &id.id, &o.item);
+        }
+        check_modifiers(&mut errs, &ManifestType::ItemData, &id.id, &id.modifiers);
+    }
+
+    for rd in Recipes.values() {
+        let kind = ManifestType::RecipeData;
+        for i in rd.inputs.iter() {
+            match (&i.item, &i.category) {
+                (Some(item), _) => check_item(&mut errs, &kind, &rd.id, item),
+                (None, Some(c)) => check_category(&mut errs, &kind, &rd.id, c),
+                (None, None) => errs.push(entry_error(&kind, &rd.id, "input has neither an item nor a category")),
+            }
+        }
+        for o in rd.outputs.iter() {
+            check_item(&mut errs, &kind, &rd.id, &o.item);
+        }
+        check_cap(&mut errs, &kind, &rd.id, &rd.capability.name);
+        check_skill(&mut errs, &kind, &rd.id, &rd.skill);
+        if let Some(m) = &rd.material {
+            if !Materials.contains_key(m) {
+                errs.push(entry_error(&kind, &rd.id, format!("unknown material '{}'", m)));
+            }
+        }
+    }
+
+    for cd in Classes.values() {
+        let id = cd.id();
+        for s in cd.skills.iter() {
+            check_skill(&mut errs, &ManifestType::ClassData, &id, s);
+        }
+        for r in cd.ranks.iter() {
+            check_modifiers(&mut errs, &ManifestType::ClassData, &id, &r.bonuses);
+        }
+    }
+
+    for t in Traits.values() {
+        check_modifiers(&mut errs, &ManifestType::TraitData, &t.name, &t.modifiers);
+    }
+
+    for sc in SkillCapabilities.values() {
+        check_skill(&mut errs, &ManifestType::SkillCapabilityData, &sc.target_cap, &sc.input_skill);
+    }
+
+    errs
+}
+
+fn check_item(errs: &mut Vec<ManifestError>, kind: &ManifestType, id: &str, item: &str) {
+    if !Items.contains_key(item) {
+        errs.push(entry_error(kind, id, format!("unknown item '{}'", item)));
+    }
+}
+
+fn check_category(errs: &mut Vec<ManifestError>, kind: &ManifestType, id: &str, category: &str) {
+    if !Categories.contains(category) {
+        errs.push(entry_error(kind, id, format!("unknown category '{}'", category)));
+    }
+}
+
+fn check_cap(errs: &mut Vec<ManifestError>, kind: &ManifestType, id: &str, cap: &str) {
+    if !SkillCapabilities.contains_key(cap) {
+        errs.push(entry_error(kind, id, format!("unknown capability '{}'", cap)));
+    }
+}
+
+fn check_skill(errs: &mut Vec<ManifestError>, kind: &ManifestType, id: &str, skill: &str) {
+    if !SKILLS.contains(&skill.to_lowercase().as_str()) {
+        errs.push(entry_error(kind, id, format!("unknown skill '{}'", skill)));
+    }
+}
+
+/// check_modifiers makes sure every modifier names exactly one known skill or attribute
+fn check_modifiers(errs: &mut Vec<ManifestError>, kind: &ManifestType, id: &str, mods: &[Modifier]) {
+    for m in mods {
+        match (&m.skill, &m.attr) {
+            (Some(s), None) => check_skill(errs, kind, id, s),
+            (None, Some(a)) => {
+                let mut known = ATTRIBUTES.iter().chain(NEED_ATTRS.iter()).chain(BODY_ATTRS.iter());
+                if !known.any(|k| *k == a.as_str()) {
+                    errs.push(entry_error(kind, id, format!("unknown attribute '{}'", a)));
+                }
+            }
+            _ => errs.push(entry_error(kind, id, "modifier needs exactly one of skill or attr")),
+        }
+    }
+}
@@ -5,15 +5,44 @@ use bevy::ecs::component::Component;
 use bevy::render::draw::{RenderCommand, OutsideFrustum};
 use bevy::gltf::{Gltf, GltfPrimitive};
 use std::ops::Range;
+use std::sync::Arc;
 use bevy::ecs::system::EntityCommands;
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use crate::sim::{
+    jobs::{JobKind, Jobs, NO_JOB},
+    world::{
+        body::{size_factor, Body},
+        class::{self, Class, Progression, UnrolledCore},
+        faction::{Faction, SpawnTags, Strength},
+        item::Inventory,
+        data::{ids::RaceId, locale, Entities, ManifestData, ManifestType, RaceData},
+        equipment::Equipment,
+        mech::Piloting,
+        needs::Needs,
+    },
+};
 
 pub struct System;
 
 impl Plugin for System {
     fn build(&self, app: &mut App) {
+        app.add_system(move_livings);
     }
 }
 
+// units per second a healthy living walks
+const WALK_SPEED: f32 = 2.;
+// how close a living has to get for a move to be done
+const ARRIVED: f32 = 0.1;
+// the weight an orderable living carries when the spawn request doesn't give an inventory
+pub const CARRY_CAPACITY: f32 = 40.;
+
+/// ATTRIBUTES are the attribute names modifiers can change
+pub const ATTRIBUTES: [&str; 6] = ["constitution", "agility", "dexterity", "strength", "luck", "intelligence"];
+/// SKILLS are the skill names with their own formula, see CoreAttributes::skill
+pub const SKILLS: [&str; 6] = ["mining", "hauling", "construction", "fabrication", "disassembly", "piloting"];
+
 #[derive(Debug, Default, Copy, Clone)]
 pub struct CoreAttributes {
     pub constitution: f32,
@@ -43,6 +72,35 @@ impl CoreAttributes {
         }
         ca
     }
+
+    /// skill is how good the attributes are at the named skill, unknown skills fall back
+    /// on dexterity. Keep SKILLS in sync with the names here.
+    pub fn skill(self, name: &str) -> f32 {
+        match name {
+            "mining" => self.mining(),
+            "hauling" => self.hauling(),
+            "construction" | "fabrication" | "disassembly" => self.building(),
+            "piloting" => self.piloting(),
+            _ => self.dexterity,
+        }
+    }
+
+    /// with_modifiers returns the attributes with any attr modifiers applied
+    pub fn with_modifiers<'a, I: IntoIterator<Item = &'a Modifier>>(mut self, mods: I) -> Self {
+        for m in mods {
+            let v = m.value as f32;
+            match m.attr.as_deref() {
+                Some("constitution") => self.constitution += v,
+                Some("agility") => self.agility += v,
+                Some("dexterity") => self.dexterity += v,
+                Some("strength") => self.strength += v,
+                Some("luck") => self.luck += v,
+                Some("intelligence") => self.intelligence += v,
+                _ => (),
+            }
+        }
+        self
+    }
 }
 
 /// what are traits, anyway?
@@ -55,14 +113,57 @@ impl CoreAttributes {
 /// traits being like little bits of dirt or hair stuck to clothes, kind of
 /// invisible things that I'm not aware of. But then if I am aware of them,
 /// like when I wash my clothes, they don't seem to go
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Trait {
     pub name: String,
     pub description: String,
     pub modifiers: Vec<Modifier>
 }
 
+impl ManifestData for Trait {
+    const ID_KEY: &'static str = "name";
+
+    fn kind() -> ManifestType {
+        ManifestType::TraitData
+    }
+
+    fn id(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl Trait {
+    /// describe is the description in the current language about the living
+    pub fn describe(&self, p: &Profile) -> String {
+        let height = locale::tr_with("units.height", &[("value", &format!("{:.0}", p.height))]);
+        let weight = locale::tr_with("units.weight", &[("value", &format!("{:.0}", p.weight))]);
+        locale::tr_with(&self.description, &[("name", &p.name), ("height", &height), ("weight", &weight)])
+    }
+}
+
+/// Profile is who a living is, for its character sheet
 #[derive(Debug, Default, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub traits: Vec<Trait>,
+    // in centimeters and kilograms, from the race and sized by the traits
+    pub height: f32,
+    pub weight: f32,
+}
+
+impl Profile {
+    pub fn new(name: &str, race: Option<&RaceData>, traits: &[Trait]) -> Self {
+        let f = size_factor(traits);
+        Self {
+            name: name.to_string(),
+            traits: traits.to_vec(),
+            height: race.and_then(|r| r.height).unwrap_or(0.) * f,
+            weight: race.and_then(|r| r.weight).unwrap_or(0.) * f,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Modifier {
     pub skill: Option<String>,
     pub attr: Option<String>,
@@ -125,8 +226,6 @@ impl Into<LocArea> for (Vec3, Vec3) {
     }
 }
 
-pub struct LivingId(i64);
-
 #[derive(Debug, Bundle, Default)]
 pub struct LivingBundle {
     pub living: Living,
@@ -137,7 +236,7 @@ pub trait SpawnLiving<'w, 's> {
     fn spawn_living<'a>(&'a mut self, sr: SpawnRequest) -> EntityCommands<'w, 's, 'a>;
 }
 
-#[derive(Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum EntityTags {
     Neutral,
     Hostile,
@@ -156,7 +255,10 @@ pub struct SpawnRequest {
     pub traits: Option<Vec<Trait>>,
     pub tags: Option<EntityTags>,
     pub living: Option<Living>,
-    // pub inventory: Option<Inventory>
+    pub class: Option<Class>,
+    // the core to give the living, rolled from its race when there's none
+    pub core: Option<class::Modifier>,
+    pub inventory: Option<Inventory>,
 }
 
 impl SpawnRequest {
@@ -180,6 +282,13 @@ impl SpawnRequest {
 
         self
     }
+
+    /// race_data looks up the race of the request, falling back to the race of the entity
+    pub fn race_data(&self) -> Option<Arc<RaceData>> {
+        RaceId::of(&self.race)
+            .or_else(|| Entities.get(&self.id).and_then(|ld| RaceId::of(&ld.race)))
+            .and_then(|id| id.data())
+    }
 }
 impl Into<Living> for SpawnRequest {
     fn into(self) -> Living {
@@ -197,8 +306,74 @@ impl Into<Living> for SpawnRequest {
 
 impl<'w, 's> SpawnLiving<'w, 's> for Commands<'w, 's> {
     fn spawn_living<'a>(&'a mut self, sr: SpawnRequest) -> EntityCommands<'w, 's, 'a> {
+        let race = sr.race_data();
+        let needs = race.as_ref().and_then(|r| {
+            Needs::for_living(r, sr.traits.as_deref().unwrap_or(&[]))
+        });
+        let body = race.as_ref().and_then(|r| r.body).map(|p| {
+            Body::for_living(p, sr.traits.as_deref().unwrap_or(&[]))
+        });
+        let progression = sr.class.map(|c| Progression {
+            core: sr.core.unwrap_or_default(),
+            ..Progression::new(c)
+        });
+        let unrolled = match (&progression, sr.core, race.as_ref()) {
+            (Some(_), None, Some(r)) if !r.cores.is_empty() => Some(UnrolledCore(r.cores.clone())),
+            _ => None,
+        };
+        // untagged spawns of an orderable race belong to the colony
+        let orderable = race.as_ref()
+            .and_then(|r| r.properties.as_ref())
+            .map(|p| p.iter().any(|p| p == "Orderable"))
+            .unwrap_or(false);
+        let profile = match (&sr.name, &sr.traits) {
+            (None, None) => None,
+            (name, traits) => Some(Profile::new(
+                name.as_deref().unwrap_or(&sr.id),
+                race.as_deref(),
+                traits.as_deref().unwrap_or(&[]),
+            )),
+        };
+        let tags = sr.tags;
+        let inventory = match (&sr.inventory, orderable) {
+            (Some(i), _) => Some(i.clone()),
+            (None, true) => Some(Inventory::new(CARRY_CAPACITY)),
+            (None, false) => None,
+        };
         let mut base = self.spawn();
         base.insert(Living::from(sr.into()));
+        if let Some(n) = needs {
+            base.insert(n);
+        }
+        if let Some(b) = body {
+            base.insert(b);
+        }
+        if let Some(p) = progression {
+            base.insert(p);
+        }
+        if let Some(u) = unrolled {
+            base.insert(u);
+        }
+        if let Some(p) = profile {
+            base.insert(p);
+        }
+        if let Some(i) = inventory {
+            base.insert(i);
+        }
+        if orderable {
+            base.insert(Equipment::default());
+        }
+        match tags {
+            Some(t) => {
+                base.insert(SpawnTags(t));
+            }
+            None if orderable => {
+                base.insert(Faction::Colony).insert(Strength::default());
+            }
+            None => {
+                base.insert(Faction::Wildlife).insert(Strength::default());
+            }
+        }
         // let mut base = self.spawn_bundle(LivingBundle {
         //     // living: ld.clone(),
         //     // transform: Transform::from_translation(ld.position.clone()),
@@ -213,10 +388,59 @@ impl<'w, 's> SpawnLiving<'w, 's> for Commands<'w, 's> {
 
 pub struct Owned(bool);
 
+/// move_livings walks each living towards the target of its job. Move jobs are done
+/// on arrival, other jobs are finished by whatever system works them.
+fn move_livings(
+    time: Res<Time>,
+    mut jobs: ResMut<Jobs>,
+    mut q: Query<(&mut Living, Option<&Body>, Option<&Equipment>), Without<Piloting>>,
+    targets: Query<&Transform>,
+) {
+    let dt = time.delta_seconds();
+    for (mut l, b, eq) in q.iter_mut() {
+        let j = match jobs.get(l.has_job) {
+            Some(j) => j,
+            None => continue,
+        };
+        let target = match j.target.and_then(|t| targets.get(t).ok()) {
+            Some(t) => t.translation,
+            None => match j.position {
+                Some(p) => p,
+                None => continue,
+            },
+        };
+        let to = target - l.position;
+        if to.length() <= ARRIVED {
+            if j.kind == JobKind::Move {
+                jobs.finish(l.has_job);
+                l.has_job = NO_JOB;
+            }
+            continue;
+        }
+        let speed = WALK_SPEED
+            * b.map(|b| b.move_speed()).unwrap_or(1.)
+            * eq.map(|e| e.move_speed()).unwrap_or(1.);
+        let step = (speed * dt).min(to.length());
+        l.position += to.normalize() * step;
+    }
+}
+
 impl Living {
     pub fn active_job(self) -> i64 {
         self.has_job
     }
+
+    /// attributes are the core attributes with rank bonuses and worn gear applied
+    pub fn attributes(&self, p: Option<&Progression>, eq: Option<&Equipment>) -> CoreAttributes {
+        let mut a = self.attrs;
+        if let Some(p) = p {
+            a = a.with_modifiers(&p.bonuses());
+        }
+        if let Some(eq) = eq {
+            a = a.with_modifiers(&eq.modifiers());
+        }
+        a
+    }
 }
 
 type Scorer<T = f32> = (T, T, T, T, T, T);
@@ -224,6 +448,7 @@ pub trait DerivedAttributes {
     fn mining(self) -> f32;
     fn hauling(self) -> f32;
     fn building(self) -> f32;
+    fn piloting(self) -> f32;
     fn score(self, weights: Scorer) -> f32;
 }
 
@@ -237,6 +462,9 @@ impl DerivedAttributes for CoreAttributes {
     fn building(self) -> f32 {
         self.score((20., 15., 25., 15., 1., 25.))
     }
+    fn piloting(self) -> f32 {
+        self.score((5., 30., 30., 5., 5., 25.))
+    }
 
     fn score(self, weights: Scorer) -> f32 {
         (self.constitution * weights.0/100.) +
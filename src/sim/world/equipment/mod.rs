@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+use bevy::utils::StableHashMap;
+use crate::sim::{
+    jobs::{JobKind, Jobs, NO_JOB},
+    world::{
+        data::ids::ItemId,
+        entity::{self, Living},
+        item::{Inventory, Item, ItemStack, OrganicSlot, Storage},
+        raw::MaterialRoll,
+    },
+};
+
+/// System equips better gear, from the living's own inventory right away and from storage
+/// through an equip job
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_system(equip_from_inventory)
+            .add_system(plan_auto_equip)
+            .add_system(work_equip_jobs);
+    }
+}
+
+// how close a living has to be to take something out of storage
+const REACH: f32 = 1.5;
+const EQUIP_PRIORITY: u8 = 3;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum EquipError {
+    NotInInventory(ItemId),
+    NotEquipable(ItemId),
+    // the item taken off doesn't fit in the inventory
+    NoRoom,
+}
+
+/// Equipment is the gear a living is wearing, one item per slot
+#[derive(Debug, Default, Clone)]
+pub struct Equipment {
+    pub slots: StableHashMap<OrganicSlot, Item>,
+}
+
+/// gear_score is how good an item is to wear, used to decide what's better
+pub fn gear_score(i: &Item) -> f32 {
+    let mods: i32 = i.data().map(|d| d.modifiers.iter().map(|m| m.value).sum()).unwrap_or(0);
+    let speed = i.data().and_then(|d| d.move_speed).unwrap_or(0);
+    i.armor() + mods as f32 * 2. + speed as f32 * 0.5 + i.durability() * 0.05
+}
+
+impl Equipment {
+    /// equip moves one of the item from the inventory into its slot, whatever was in the
+    /// slot goes back into the inventory
+    pub fn equip(&mut self, inv: &mut Inventory, id: ItemId) -> Result<OrganicSlot, EquipError> {
+        let item = inv.stacks.iter().rev()
+            .find(|s| s.item.id == id)
+            .map(|s| s.item.clone())
+            .ok_or(EquipError::NotInInventory(id))?;
+        self.equip_item(inv, item)
+    }
+
+    /// equip_item is equip for one particular item out of the inventory, rather than any
+    /// item with its id
+    pub fn equip_item(&mut self, inv: &mut Inventory, item: Item) -> Result<OrganicSlot, EquipError> {
+        if !inv.stacks.iter().any(|s| s.item.stacks_with(&item)) {
+            return Err(EquipError::NotInInventory(item.id));
+        }
+        let slot = item.slot().ok_or(EquipError::NotEquipable(item.id))?;
+        if let Some(old) = self.slots.get(&slot) {
+            // the new item leaves the inventory, so its weight frees up room
+            if inv.free() + item.weight() < old.weight() {
+                return Err(EquipError::NoRoom);
+            }
+        }
+        inv.remove_item(&item, 1);
+        if let Some(old) = self.slots.insert(slot, item) {
+            inv.add(ItemStack::new(old, 1));
+        }
+        Ok(slot)
+    }
+
+    /// unequip takes the item out of the slot and puts it in the inventory
+    pub fn unequip(&mut self, inv: &mut Inventory, slot: OrganicSlot) -> Result<(), EquipError> {
+        let item = match self.slots.get(&slot) {
+            Some(i) => i,
+            None => return Ok(()),
+        };
+        if inv.fits(item) < 1 {
+            return Err(EquipError::NoRoom);
+        }
+        let item = self.slots.remove(&slot).unwrap();
+        inv.add(ItemStack::new(item, 1));
+        Ok(())
+    }
+
+    pub fn is_better(&self, i: &Item) -> bool {
+        let slot = match i.slot() {
+            Some(s) => s,
+            None => return false,
+        };
+        match self.slots.get(&slot) {
+            Some(cur) => gear_score(i) > gear_score(cur),
+            None => true,
+        }
+    }
+
+    /// modifiers are the attribute modifiers of all worn gear
+    pub fn modifiers(&self) -> Vec<entity::Modifier> {
+        self.slots.values()
+            .filter_map(|i| i.data())
+            .flat_map(|d| d.modifiers.clone())
+            .collect()
+    }
+
+    /// armor_at is the armor of what's worn in the slot
+    pub fn armor_at(&self, slot: OrganicSlot) -> f32 {
+        self.slots.get(&slot).map(|i| i.armor()).unwrap_or(0.)
+    }
+
+    /// move_speed is the movement multiplier of all worn gear
+    pub fn move_speed(&self) -> f32 {
+        let pct: i32 = self.slots.values()
+            .filter_map(|i| i.data().and_then(|d| d.move_speed))
+            .sum();
+        MaterialRoll::apply(1., pct)
+    }
+}
+
+/// equip_from_inventory puts on the best item of each slot in a living's inventory, when
+/// it beats what it wears
+fn equip_from_inventory(mut q: Query<(&mut Inventory, &mut Equipment), With<Living>>) {
+    for (mut inv, mut eq) in q.iter_mut() {
+        let mut best: StableHashMap<OrganicSlot, &Item> = Default::default();
+        for s in inv.stacks.iter().filter(|s| eq.is_better(&s.item)) {
+            let slot = match s.item.slot() {
+                Some(slot) => slot,
+                None => continue,
+            };
+            match best.get(&slot) {
+                Some(b) if gear_score(b) >= gear_score(&s.item) => {}
+                _ => {
+                    best.insert(slot, &s.item);
+                }
+            }
+        }
+        let best: Vec<Item> = best.into_iter().map(|(_, i)| i.clone()).collect();
+        for item in best {
+            let _ = eq.equip_item(&mut inv, item);
+        }
+    }
+}
+
+/// plan_auto_equip sends idle livings to fetch better gear from storage
+fn plan_auto_equip(
+    mut jobs: ResMut<Jobs>,
+    mut livings: Query<(Entity, &mut Living, &Equipment)>,
+    storage: Query<(Entity, &Inventory), With<Storage>>,
+) {
+    for (e, mut l, eq) in livings.iter_mut() {
+        if l.has_job != NO_JOB {
+            continue;
+        }
+        let taken: Vec<(Entity, ItemId)> = jobs.list.iter()
+            .filter(|j| j.kind == JobKind::Equip)
+            .filter_map(|j| Some((j.target?, j.item?)))
+            .collect();
+        let found = storage.iter().find_map(|(se, inv)| {
+            inv.stacks.iter()
+                .filter(|s| !taken.contains(&(se, s.item.id)))
+                .find(|s| eq.is_better(&s.item))
+                .map(|s| (se, s.item.id))
+        });
+        if let Some((se, id)) = found {
+            let jid = jobs.push_item(JobKind::Equip, EQUIP_PRIORITY, se, id, 1);
+            jobs.assign(jid, e);
+            l.has_job = jid;
+        }
+    }
+}
+
+/// work_equip_jobs takes the gear out of storage once the living reached it
+fn work_equip_jobs(
+    mut jobs: ResMut<Jobs>,
+    mut livings: Query<(&mut Living, &mut Inventory, &mut Equipment)>,
+    mut storage: Query<(&mut Inventory, &Transform), (With<Storage>, Without<Living>)>,
+) {
+    for (mut l, mut inv, mut eq) in livings.iter_mut() {
+        let j = match jobs.get(l.has_job) {
+            Some(j) if j.kind == JobKind::Equip => j.clone(),
+            _ => continue,
+        };
+        let (item, target) = match (j.item, j.target) {
+            (Some(i), Some(t)) => (i, t),
+            _ => continue,
+        };
+        let (mut store, t) = match storage.get_mut(target) {
+            Ok(s) => s,
+            Err(_) => {
+                jobs.finish(j.id);
+                l.has_job = NO_JOB;
+                continue;
+            }
+        };
+        if t.translation.distance(l.position) > REACH {
+            continue;
+        }
+        if store.transfer(&mut inv, item, 1) == 1 {
+            if let Err(e) = eq.equip(&mut inv, item) {
+                debug!("can't equip {}: {:?}", item, e);
+            }
+        }
+        jobs.finish(j.id);
+        l.has_job = NO_JOB;
+    }
+}
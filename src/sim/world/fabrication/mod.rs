@@ -0,0 +1,310 @@
+use bevy::prelude::*;
+use std::sync::Arc;
+use crate::sim::{
+    generator::SimRng,
+    jobs::{JobId, JobKind, Jobs, NO_JOB},
+    world::{
+        body::Body,
+        buildings::Building,
+        class::{ExperienceEvent, Progression},
+        data::{ids::ItemId, RecipeData, RecipeInput, Recipes},
+        equipment::Equipment,
+        entity::Living,
+        item::{roll_quality, Inventory, Item, ItemStack, SpawnItem, Storage},
+        raw::MaterialRoll,
+    },
+};
+
+/// System turns workstation bills into fabrication jobs and works them
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_system(plan_fabrication)
+            .add_system(work_fabrication);
+    }
+}
+
+// how close a worker has to be to use the workstation
+const REACH: f32 = 1.5;
+// storage this close to a workstation is used for its ingredients
+pub const STORAGE_RANGE: f32 = 8.;
+const FABRICATE_PRIORITY: u8 = 5;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BillMode {
+    // make this many, then stop
+    Count(u32),
+    // keep making until there are this many in storage
+    UntilStock(u32),
+    Forever,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BillTask {
+    // make the recipe with this id
+    Make(String),
+    // break down items, optionally only ones of a category
+    Salvage(Option<String>),
+}
+
+/// Bill is an order at a workstation to make a recipe, or to salvage items
+#[derive(Debug, Clone)]
+pub struct Bill {
+    pub task: BillTask,
+    pub mode: BillMode,
+    pub done: u32,
+    pub suspended: bool,
+}
+
+impl Bill {
+    pub fn new(recipe: &str, mode: BillMode) -> Self {
+        Self {
+            task: BillTask::Make(recipe.to_string()),
+            mode,
+            done: 0,
+            suspended: false,
+        }
+    }
+
+    pub fn salvage(category: Option<&str>, mode: BillMode) -> Self {
+        Self {
+            task: BillTask::Salvage(category.map(|c| c.to_string())),
+            mode,
+            done: 0,
+            suspended: false,
+        }
+    }
+
+    /// recipe is the recipe of a Make bill
+    pub fn recipe(&self) -> Option<Arc<RecipeData>> {
+        match &self.task {
+            BillTask::Make(r) => Recipes.get(r),
+            BillTask::Salvage(_) => None,
+        }
+    }
+
+    /// is_active is true while the bill still wants something made, given how many of
+    /// its first output are in storage
+    pub fn is_active(&self, stock: u32) -> bool {
+        if self.suspended {
+            return false;
+        }
+        match self.mode {
+            BillMode::Count(n) => self.done < n,
+            BillMode::UntilStock(n) => stock < n,
+            BillMode::Forever => true,
+        }
+    }
+}
+
+/// Workstation is a usable building with a queue of bills. It works one bill at a time.
+#[derive(Debug, Default, Clone)]
+pub struct Workstation {
+    pub bills: Vec<Bill>,
+    pub job: JobId,
+    pub bill: Option<usize>,
+    // seconds of work done on the current job, ingredients are taken when it starts
+    pub progress: f32,
+    pub started: bool,
+    // the item being taken apart by a salvage job
+    pub salvaging: Option<Item>,
+}
+
+impl Workstation {
+    pub fn add_bill(&mut self, b: Bill) {
+        self.bills.push(b);
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.job = NO_JOB;
+        self.bill = None;
+        self.progress = 0.;
+        self.started = false;
+        self.salvaging = None;
+    }
+}
+
+pub fn input_matches(input: &RecipeInput, item: &Item) -> bool {
+    if let Some(id) = &input.item {
+        return ItemId::of(id) == Some(item.id);
+    }
+    match &input.category {
+        Some(c) => item.is_a(c),
+        None => false,
+    }
+}
+
+/// has_inputs is true when the inventories hold everything the recipe needs
+pub fn has_inputs<'a, I>(r: &RecipeData, invs: I) -> bool
+    where I: Iterator<Item = &'a Inventory> + Clone {
+    r.inputs.iter().all(|input| {
+        let have: u32 = invs.clone()
+            .flat_map(|inv| inv.stacks.iter())
+            .filter(|s| input_matches(input, &s.item))
+            .map(|s| s.count)
+            .sum();
+        have >= input.count
+    })
+}
+
+/// take_inputs removes the recipe's inputs from the inventories, in order. Nothing is
+/// taken unless everything is there.
+pub fn take_inputs(r: &RecipeData, invs: &mut [Mut<Inventory>]) -> bool {
+    if !has_inputs(r, invs.iter().map(|i| &**i)) {
+        return false;
+    }
+    for input in r.inputs.iter() {
+        let mut left = input.count;
+        for inv in invs.iter_mut() {
+            let ids: Vec<ItemId> = inv.stacks.iter()
+                .filter(|s| input_matches(input, &s.item))
+                .map(|s| s.item.id)
+                .collect();
+            for id in ids {
+                if left == 0 {
+                    break;
+                }
+                if let Some(s) = inv.remove(id, left) {
+                    left -= s.count;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// plan_fabrication queues a job for the first bill of each idle workstation that can be
+/// made there with the ingredients in nearby storage
+fn plan_fabrication(
+    mut jobs: ResMut<Jobs>,
+    mut stations: Query<(Entity, &Building, &mut Workstation, &Transform)>,
+    storage: Query<(&Inventory, &Transform), With<Storage>>,
+) {
+    for (e, b, mut ws, t) in stations.iter_mut() {
+        if ws.job != NO_JOB {
+            if jobs.get(ws.job).is_some() {
+                continue;
+            }
+            ws.reset();
+        }
+        let bd = match b.data() {
+            Some(bd) => bd,
+            None => continue,
+        };
+        let nearby: Vec<&Inventory> = storage.iter()
+            .filter(|(_, st)| st.translation.distance(t.translation) <= STORAGE_RANGE)
+            .map(|(i, _)| i)
+            .collect();
+        let next = ws.bills.iter().enumerate().find_map(|(idx, bill)| {
+            let r = bill.recipe()?;
+            let stock: u32 = match r.outputs.first().and_then(|o| ItemId::of(&o.item)) {
+                Some(id) => storage.iter().map(|(i, _)| i.count(id)).sum(),
+                None => 0,
+            };
+            if !bill.is_active(stock) {
+                return None;
+            }
+            if bd.cap_level(&r.capability.name).unwrap_or(0) < r.capability.level {
+                return None;
+            }
+            if !has_inputs(&r, nearby.iter().copied()) {
+                return None;
+            }
+            Some((idx, r))
+        });
+        if let Some((idx, r)) = next {
+            ws.job = jobs.push_recipe(JobKind::Fabricate, FABRICATE_PRIORITY, e, &r.id);
+            jobs.require(ws.job, &r.skill, r.min_skill);
+            ws.bill = Some(idx);
+        }
+    }
+}
+
+/// work_fabrication takes the ingredients when work starts and puts the outputs down next
+/// to the workstation when it's done
+fn work_fabrication(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut rng: ResMut<SimRng>,
+    mut jobs: ResMut<Jobs>,
+    mut xp: EventWriter<ExperienceEvent>,
+    mut livings: Query<(Entity, &mut Living, Option<&Body>, Option<&Progression>, Option<&Equipment>)>,
+    mut stations: Query<(&Building, &mut Workstation, &Transform)>,
+    mut storage: Query<(&mut Inventory, &Transform), With<Storage>>,
+) {
+    let dt = time.delta_seconds();
+    for (e, mut l, body, p, eq) in livings.iter_mut() {
+        let j = match jobs.get(l.has_job) {
+            Some(j) if j.kind == JobKind::Fabricate => j.clone(),
+            _ => continue,
+        };
+        let r = match j.recipe.as_ref().and_then(|r| Recipes.get(r)) {
+            Some(r) => r,
+            None => {
+                jobs.finish(j.id);
+                l.has_job = NO_JOB;
+                continue;
+            }
+        };
+        let (b, mut ws, t) = match j.target.and_then(|t| stations.get_mut(t).ok()) {
+            Some(s) => s,
+            None => {
+                jobs.finish(j.id);
+                l.has_job = NO_JOB;
+                continue;
+            }
+        };
+        if t.translation.distance(l.position) > REACH {
+            continue;
+        }
+
+        if !ws.started {
+            let mut nearby: Vec<Mut<Inventory>> = storage.iter_mut()
+                .filter(|(_, st)| st.translation.distance(t.translation) <= STORAGE_RANGE)
+                .map(|(i, _)| i)
+                .collect();
+            if !take_inputs(&r, &mut nearby) {
+                // someone took the ingredients, plan again later
+                jobs.finish(j.id);
+                ws.reset();
+                l.has_job = NO_JOB;
+                continue;
+            }
+            ws.started = true;
+        }
+
+        let attrs = l.attributes(p, eq);
+        let skill = attrs.skill(&r.skill);
+        let speed = skill.max(1.) * body.map(|b| b.manipulation()).unwrap_or(1.);
+        ws.progress += speed * dt;
+        if ws.progress < r.work_time {
+            continue;
+        }
+
+        let cap_level = b.data().and_then(|d| d.cap_level(&r.capability.name)).unwrap_or(0);
+        let quality = roll_quality(skill, attrs.dexterity, attrs.luck, cap_level, &mut rng.0);
+        for o in r.outputs.iter() {
+            let mut item = match ItemId::of(&o.item).and_then(Item::new) {
+                Some(i) => i.with_quality(quality),
+                None => continue,
+            };
+            if let Some(m) = r.material.as_ref().and_then(|m| MaterialRoll::roll_id(m, &mut rng.0)) {
+                item = item.made_of(m);
+            }
+            commands.spawn_item(ItemStack::new(item, o.count), t.translation);
+        }
+        xp.send(ExperienceEvent {
+            entity: e,
+            skill: r.skill.clone(),
+            amount: r.work_time,
+        });
+        let idx = ws.bill;
+        if let Some(bill) = idx.and_then(|i| ws.bills.get_mut(i)) {
+            bill.done += 1;
+        }
+        jobs.finish(j.id);
+        ws.reset();
+        l.has_job = NO_JOB;
+    }
+}
@@ -0,0 +1,200 @@
+use bevy::prelude::*;
+use bevy::utils::StableHashMap;
+use rand::Rng;
+use crate::sim::{
+    generator::SimRng,
+    world::{body::Body, entity::EntityTags},
+};
+
+/// System keeps the relations between factions and puts spawned livings into one
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Relations>()
+            .add_system(resolve_spawn_tags);
+    }
+}
+
+pub const STANDING_MIN: i32 = -100;
+pub const STANDING_MAX: i32 = 100;
+
+// strength of a Strong spawn, and the range RandomStrong spawns roll in
+const STRONG: f32 = 2.;
+const RANDOM_STRONG_MIN: f32 = 1.5;
+const RANDOM_STRONG_MAX: f32 = 3.;
+// chance a Random spawn turns out hostile
+const RANDOM_HOSTILE_CHANCE: f64 = 0.5;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Faction {
+    Colony,
+    Wildlife,
+    Darkness,
+    Civilization(u16),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Disposition {
+    Hostile,
+    Wary,
+    Neutral,
+    Friendly,
+    Allied,
+}
+
+impl Disposition {
+    pub fn from_standing(s: i32) -> Self {
+        match s {
+            s if s <= -50 => Disposition::Hostile,
+            s if s < -10 => Disposition::Wary,
+            s if s <= 10 => Disposition::Neutral,
+            s if s < 50 => Disposition::Friendly,
+            _ => Disposition::Allied,
+        }
+    }
+}
+
+/// Strength scales the hp of a living's body, 1 is normal
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Strength(pub f32);
+
+impl Strength {
+    pub fn apply(self, b: &mut Body) {
+        b.scale(self.0);
+    }
+}
+
+impl Default for Strength {
+    fn default() -> Self {
+        Strength(1.)
+    }
+}
+
+/// SpawnTags holds the tags of a spawn request until they're resolved into a faction
+#[derive(Debug, Copy, Clone)]
+pub struct SpawnTags(pub EntityTags);
+
+/// Relations is the standing of every faction towards every other, from STANDING_MIN
+/// to STANDING_MAX. Standings are symmetric.
+#[derive(Debug, Clone)]
+pub struct Relations {
+    standings: StableHashMap<(Faction, Faction), i32>,
+}
+
+impl Default for Relations {
+    fn default() -> Self {
+        let mut r = Relations {
+            standings: Default::default(),
+        };
+        r.set(Faction::Colony, Faction::Darkness, STANDING_MIN);
+        r.set(Faction::Wildlife, Faction::Darkness, STANDING_MIN);
+        r
+    }
+}
+
+impl Relations {
+    fn key(a: Faction, b: Faction) -> (Faction, Faction) {
+        // order the pair so (a, b) and (b, a) share an entry
+        (a.min(b), a.max(b))
+    }
+
+    pub fn standing(&self, a: Faction, b: Faction) -> i32 {
+        if a == b {
+            return STANDING_MAX;
+        }
+        self.standings.get(&Self::key(a, b)).copied().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, a: Faction, b: Faction, s: i32) {
+        if a == b {
+            return;
+        }
+        self.standings.insert(Self::key(a, b), s.clamp(STANDING_MIN, STANDING_MAX));
+    }
+
+    pub fn adjust(&mut self, a: Faction, b: Faction, delta: i32) {
+        self.set(a, b, self.standing(a, b) + delta);
+    }
+
+    pub fn disposition(&self, a: Faction, b: Faction) -> Disposition {
+        Disposition::from_standing(self.standing(a, b))
+    }
+
+    pub fn is_hostile(&self, a: Faction, b: Faction) -> bool {
+        self.disposition(a, b) == Disposition::Hostile
+    }
+
+    /// will_trade is true for anyone that isn't at least wary of the other
+    pub fn will_trade(&self, a: Faction, b: Faction) -> bool {
+        self.disposition(a, b) >= Disposition::Neutral
+    }
+}
+
+/// resolve maps spawn tags to a faction and strength, rolling the random ones
+pub fn resolve<R: Rng>(tags: EntityTags, rng: &mut R) -> (Faction, Strength) {
+    match tags {
+        EntityTags::Neutral => (Faction::Wildlife, Strength::default()),
+        EntityTags::Hostile => (Faction::Darkness, Strength::default()),
+        EntityTags::Strong => (Faction::Darkness, Strength(STRONG)),
+        EntityTags::Random => {
+            let f = if rng.gen_bool(RANDOM_HOSTILE_CHANCE) { Faction::Darkness } else { Faction::Wildlife };
+            (f, Strength::default())
+        }
+        EntityTags::RandomStrong => {
+            let f = if rng.gen_bool(RANDOM_HOSTILE_CHANCE) { Faction::Darkness } else { Faction::Wildlife };
+            (f, Strength(rng.gen_range(RANDOM_STRONG_MIN..=RANDOM_STRONG_MAX)))
+        }
+    }
+}
+
+fn resolve_spawn_tags(
+    mut commands: Commands,
+    mut rng: ResMut<SimRng>,
+    mut q: Query<(Entity, &SpawnTags, Option<&mut Body>)>,
+) {
+    for (e, t, b) in q.iter_mut() {
+        let (f, s) = resolve(t.0, &mut rng.0);
+        if let Some(mut b) = b {
+            s.apply(&mut b);
+        }
+        commands.entity(e)
+            .insert(f)
+            .insert(s)
+            .remove::<SpawnTags>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{
+        generator::WorldOptions,
+        world::body::BodyPlan,
+    };
+
+    #[test]
+    fn strong_spawns_have_tougher_bodies() {
+        let mut rng = SimRng::default();
+        let (f, s) = resolve(EntityTags::Strong, &mut rng.0);
+        assert_eq!(f, Faction::Darkness);
+        let mut b = Body::new(BodyPlan::Organic);
+        s.apply(&mut b);
+        for p in b.parts.values() {
+            assert_eq!(p.max_hp, p.slot.base_hp() * STRONG);
+            assert_eq!(p.hp, p.max_hp);
+        }
+    }
+
+    #[test]
+    fn random_spawns_follow_the_seed() {
+        let opts = WorldOptions { seed: "mechanofence".to_string(), ..Default::default() };
+        let roll = || {
+            let mut rng = SimRng::new(&opts);
+            (0..16).map(|_| resolve(EntityTags::RandomStrong, &mut rng.0)).collect::<Vec<_>>()
+        };
+        let rolls = roll();
+        assert_eq!(rolls, roll());
+        assert!(rolls.iter().all(|(_, s)| (RANDOM_STRONG_MIN..=RANDOM_STRONG_MAX).contains(&s.0)));
+    }
+}
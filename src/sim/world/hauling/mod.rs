@@ -0,0 +1,219 @@
+use bevy::prelude::*;
+use std::cmp::Ordering;
+use crate::sim::{
+    jobs::{Job, JobKind, Jobs, NO_JOB},
+    world::{
+        buildings::Blueprint,
+        class::ExperienceEvent,
+        data::ids::ItemId,
+        entity::Living,
+        item::{Inventory, Item, ItemStack, SpawnItem, Storage},
+        stockpile::{Stockpile, DEFAULT_PRIORITY},
+    },
+};
+
+/// System plans haul jobs for loose items and blueprint materials, and works them
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_system(plan_hauling)
+            .add_system(work_hauling);
+    }
+}
+
+// how close a hauler has to be to pick up or put down
+const REACH: f32 = 1.5;
+const HAUL_PRIORITY: u8 = 2;
+// materials for blueprints go before tidying up
+const SUPPLY_PRIORITY: u8 = 4;
+pub const HAUL_SKILL: &str = "hauling";
+const HAUL_XP: f32 = 5.;
+
+/// is_carrying is true once a haul job's items have been picked up, from then on the
+/// job targets where they go and belongs to whoever holds them
+fn is_carrying(j: &Job) -> bool {
+    j.holder.is_some()
+}
+
+/// reserved is how many of the item haul jobs are still going to take from the source
+pub fn reserved(jobs: &Jobs, source: Entity, id: ItemId) -> u32 {
+    jobs.list.iter()
+        .filter(|j| j.kind == JobKind::Haul && !is_carrying(j))
+        .filter(|j| j.target == Some(source) && j.item == Some(id))
+        .map(|j| j.count)
+        .sum()
+}
+
+/// incoming is how many of the item haul jobs are bringing to the destination
+pub fn incoming(jobs: &Jobs, dest: Entity, id: ItemId) -> u32 {
+    jobs.list.iter()
+        .filter(|j| j.kind == JobKind::Haul && j.dest == Some(dest))
+        .filter(|j| j.item == Some(id))
+        .map(|j| j.count)
+        .sum()
+}
+
+/// reserved_space is the weight haul jobs are bringing to the destination
+pub fn reserved_space(jobs: &Jobs, dest: Entity) -> f32 {
+    jobs.list.iter()
+        .filter(|j| j.kind == JobKind::Haul && j.dest == Some(dest))
+        .filter_map(|j| Some(Item::new(j.item?)?.weight() * j.count as f32))
+        .sum()
+}
+
+/// plan_hauling queues jobs to carry loose items to the best stockpile that takes them,
+/// and to bring blueprints the materials they still miss. Items and space already
+/// promised to another haul job are left alone.
+fn plan_hauling(
+    mut jobs: ResMut<Jobs>,
+    loose: Query<(Entity, &ItemStack, &Transform)>,
+    stores: Query<(Entity, &Inventory, &Transform, Option<&Stockpile>), With<Storage>>,
+    blueprints: Query<(Entity, &Blueprint, &Inventory, &Transform)>,
+) {
+    for (e, stack, t) in loose.iter() {
+        let available = stack.count.saturating_sub(reserved(&jobs, e, stack.item.id));
+        if available == 0 {
+            continue;
+        }
+        let w = stack.item.weight();
+        let best = stores.iter()
+            .filter(|(_, _, _, sp)| sp.map(|sp| sp.filter.accepts(&stack.item)).unwrap_or(true))
+            .filter_map(|(se, inv, st, sp)| {
+                let room = (inv.free() - reserved_space(&jobs, se)).max(0.);
+                let fits = if w <= 0. { available } else { (room / w).floor() as u32 };
+                if fits == 0 {
+                    return None;
+                }
+                let priority = sp.map(|sp| sp.priority).unwrap_or(DEFAULT_PRIORITY);
+                Some((se, fits, priority, st.translation.distance(t.translation)))
+            })
+            .max_by(|a, b| a.2.cmp(&b.2).then(b.3.partial_cmp(&a.3).unwrap_or(Ordering::Equal)));
+        if let Some((se, fits, _, _)) = best {
+            jobs.push_haul(HAUL_PRIORITY, e, se, stack.item.id, available.min(fits));
+        }
+    }
+
+    for (be, bp, delivered, bt) in blueprints.iter() {
+        if bp.job != NO_JOB {
+            continue;
+        }
+        for (id, missing) in bp.missing(delivered) {
+            let mut need = missing.saturating_sub(incoming(&jobs, be, id));
+            while need > 0 {
+                let source = stores.iter()
+                    .map(|(se, inv, st, _)| (se, inv.count(id), st.translation))
+                    .chain(loose.iter()
+                        .filter(|(_, s, _)| s.item.id == id)
+                        .map(|(le, s, lt)| (le, s.count, lt.translation)))
+                    .map(|(se, have, p)| (se, have.saturating_sub(reserved(&jobs, se, id)), p))
+                    .filter(|(_, have, _)| *have > 0)
+                    .min_by(|a, b| {
+                        a.2.distance(bt.translation)
+                            .partial_cmp(&b.2.distance(bt.translation))
+                            .unwrap_or(Ordering::Equal)
+                    });
+                let (se, have, _) = match source {
+                    Some(s) => s,
+                    None => break,
+                };
+                let n = have.min(need);
+                jobs.push_haul(SUPPLY_PRIORITY, se, be, id, n);
+                need -= n;
+            }
+        }
+    }
+}
+
+/// work_hauling picks the items up from the source, then carries them to the
+/// destination. Whatever doesn't fit there is dropped on the floor.
+fn work_hauling(
+    mut commands: Commands,
+    mut jobs: ResMut<Jobs>,
+    mut xp: EventWriter<ExperienceEvent>,
+    mut livings: Query<(Entity, &mut Living, &mut Inventory)>,
+    mut loose: Query<(Entity, &mut ItemStack, &Transform)>,
+    mut stores: Query<(&mut Inventory, &Transform), Without<Living>>,
+) {
+    for (e, mut l, mut inv) in livings.iter_mut() {
+        let j = match jobs.get(l.has_job) {
+            Some(j) if j.kind == JobKind::Haul => j.clone(),
+            _ => continue,
+        };
+        let (id, src, dest) = match (j.item, j.target, j.dest) {
+            (Some(i), Some(s), Some(d)) => (i, s, d),
+            _ => {
+                jobs.finish(j.id);
+                l.has_job = NO_JOB;
+                continue;
+            }
+        };
+
+        if !is_carrying(&j) {
+            let picked = if let Ok((le, mut stack, t)) = loose.get_mut(src) {
+                if t.translation.distance(l.position) > REACH {
+                    continue;
+                }
+                let n = j.count.min(stack.count).min(inv.fits(&stack.item));
+                let mut taken = stack.split(n);
+                if let Some(back) = inv.add(taken.clone()) {
+                    // fits said it would fit, leave the rest on the floor
+                    stack.count += back.count;
+                    taken.count -= back.count;
+                }
+                if stack.count == 0 {
+                    commands.entity(le).despawn_recursive();
+                }
+                taken.count
+            } else if let Ok((mut store, t)) = stores.get_mut(src) {
+                if t.translation.distance(l.position) > REACH {
+                    continue;
+                }
+                store.transfer(&mut inv, id, j.count)
+            } else {
+                0
+            };
+            if picked == 0 {
+                jobs.finish(j.id);
+                l.has_job = NO_JOB;
+                continue;
+            }
+            // if the hauler is interrupted from here on, the job waits for it to come back
+            // rather than going to someone without the items
+            if let Some(job) = jobs.get_mut(j.id) {
+                job.target = Some(dest);
+                job.count = picked;
+                job.holder = Some(e);
+            }
+            continue;
+        }
+
+        if inv.count(id) == 0 {
+            // the items were used or dropped in the meantime, there's nothing to deliver
+            jobs.finish(j.id);
+            l.has_job = NO_JOB;
+            continue;
+        }
+        let left = match stores.get_mut(dest) {
+            Ok((mut store, t)) => {
+                if t.translation.distance(l.position) > REACH {
+                    continue;
+                }
+                j.count - inv.transfer(&mut store, id, j.count)
+            }
+            Err(_) => j.count,
+        };
+        if left > 0 {
+            if let Some(s) = inv.remove(id, left) {
+                commands.spawn_item(s, l.position);
+            }
+        }
+        xp.send(ExperienceEvent {
+            entity: e,
+            skill: HAUL_SKILL.to_string(),
+            amount: HAUL_XP,
+        });
+        jobs.finish(j.id);
+        l.has_job = NO_JOB;
+    }
+}
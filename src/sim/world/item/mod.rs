@@ -1,17 +1,45 @@
-enum Trait {
+use bevy::prelude::*;
+use bevy::ecs::system::EntityCommands;
+use rand::Rng;
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use crate::sim::world::{
+    category::Categories,
+    data::{assets::DataChanged, ids::ItemId, ItemData, ManifestType},
+    equipment::Equipment,
+    needs::Food,
+    raw::MaterialRoll,
+};
+
+/// System keeps the items in the world up to date with their data
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_system(refresh_items);
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum Trait {
     Equipable,
 }
 
-enum OrganicSlot {
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum OrganicSlot {
     Legs,
     Arms,
     Shoulders,
     Head,
     Chest,
-    Hands
+    Hands,
+    Feet,
 }
 
-enum MechSlot {
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum MechSlot {
+    #[default]
     Head,
     Locomotion,
     Arms,
@@ -22,8 +50,445 @@ enum MechSlot {
     Drone,
 }
 
-struct Item {
-    name: String,
-    quality: i32,
-    attractiveness: i32
-}
\ No newline at end of file
+pub const QUALITY_MAX: i32 = 100;
+pub const CONDITION_MAX: i32 = 100;
+// quality of things nobody crafted
+const QUALITY_DEFAULT: i32 = 50;
+
+/// QualityTier is the named band an item's quality falls into
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum QualityTier {
+    Awful,
+    Poor,
+    Normal,
+    Good,
+    Excellent,
+    Masterwork,
+    Legendary,
+}
+
+impl QualityTier {
+    pub fn from_quality(q: i32) -> Self {
+        match q {
+            q if q < 10 => QualityTier::Awful,
+            q if q < 30 => QualityTier::Poor,
+            q if q < 60 => QualityTier::Normal,
+            q if q < 80 => QualityTier::Good,
+            q if q < 92 => QualityTier::Excellent,
+            q if q < 99 => QualityTier::Masterwork,
+            _ => QualityTier::Legendary,
+        }
+    }
+
+    /// multiplier scales the stats of an item
+    pub fn multiplier(self) -> f32 {
+        match self {
+            QualityTier::Awful => 0.6,
+            QualityTier::Poor => 0.8,
+            QualityTier::Normal => 1.,
+            QualityTier::Good => 1.15,
+            QualityTier::Excellent => 1.3,
+            QualityTier::Masterwork => 1.5,
+            QualityTier::Legendary => 1.8,
+        }
+    }
+}
+
+/// roll_quality rolls the quality of a crafted item. Skill and dexterity raise the
+/// average, the workstation's capability level raises the floor and luck tips the roll.
+pub fn roll_quality<R: Rng>(skill: f32, dexterity: f32, luck: f32, cap_level: i32, rng: &mut R) -> i32 {
+    let base = 10. + skill * 4. + dexterity * 2. + cap_level as f32 * 8.;
+    let swing = rng.gen_range(-20.0..=20.0) + luck * 1.5;
+    ((base + swing).round() as i32).clamp(0, QUALITY_MAX)
+}
+
+/// Item is a single thing, what it is comes from its ItemData
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub id: ItemId,
+    pub name: String,
+    pub quality: i32,
+    pub attractiveness: i32,
+    pub material: Option<MaterialRoll>,
+    // how intact the item is, from 0 (wrecked) to CONDITION_MAX
+    pub condition: i32,
+}
+
+impl Item {
+    pub fn new(id: ItemId) -> Option<Self> {
+        let d = id.data()?;
+        Some(Self {
+            id,
+            name: d.name.clone(),
+            quality: QUALITY_DEFAULT,
+            attractiveness: d.beauty.unwrap_or(0),
+            material: None,
+            condition: CONDITION_MAX,
+        })
+    }
+
+    /// with_quality sets the quality, better made things are nicer to look at
+    pub fn with_quality(mut self, q: i32) -> Self {
+        self.quality = q.clamp(0, QUALITY_MAX);
+        self.attractiveness = self.beauty();
+        self
+    }
+
+    fn beauty(&self) -> i32 {
+        let base = self.data().and_then(|d| d.beauty).unwrap_or(0) as f32;
+        if base >= 0. {
+            (base * self.tier().multiplier()).round() as i32
+        } else {
+            // ugly things get less ugly with quality
+            (base / self.tier().multiplier()).round() as i32
+        }
+    }
+
+    /// refresh works the name and attractiveness out again, for when the data was
+    /// reloaded. Items whose data is gone are left as they are.
+    pub fn refresh(&mut self) {
+        let d = match self.data() {
+            Some(d) => d,
+            None => return,
+        };
+        let mn = self.material.as_ref().map(|m| m.name()).unwrap_or_default();
+        self.name = if mn.is_empty() {
+            d.name.clone()
+        } else {
+            format!("{} {}", mn, d.name)
+        };
+        self.attractiveness = self.beauty();
+    }
+
+    pub fn tier(&self) -> QualityTier {
+        QualityTier::from_quality(self.quality)
+    }
+
+    /// made_of sets what the item was made from, the name picks up the material
+    pub fn made_of(mut self, m: MaterialRoll) -> Self {
+        let mn = m.name();
+        if !mn.is_empty() {
+            self.name = format!("{} {}", mn, self.name);
+        }
+        self.material = Some(m);
+        self
+    }
+
+    pub fn data(&self) -> Option<Arc<ItemData>> {
+        self.id.data()
+    }
+
+    pub fn weight(&self) -> f32 {
+        let w = self.data().map(|d| d.weight).unwrap_or(0.);
+        match &self.material {
+            Some(m) => MaterialRoll::apply(w, m.weight),
+            None => w,
+        }
+    }
+
+    pub fn damage(&self) -> f32 {
+        let d = self.data().and_then(|d| d.damage).unwrap_or(0.) * self.tier().multiplier();
+        match &self.material {
+            Some(m) => MaterialRoll::apply(d, m.damage),
+            None => d,
+        }
+    }
+
+    pub fn durability(&self) -> f32 {
+        let d = self.data().and_then(|d| d.durability).unwrap_or(0.) * self.tier().multiplier();
+        match &self.material {
+            Some(m) => MaterialRoll::apply(d, m.durability),
+            None => d,
+        }
+    }
+
+    pub fn stack_size(&self) -> u32 {
+        self.data().map(|d| d.stack_size).unwrap_or(1).max(1)
+    }
+
+    pub fn category(&self) -> Option<String> {
+        self.data().and_then(|d| d.category.clone())
+    }
+
+    /// is_a is true when the item's category is the given one or below it
+    pub fn is_a(&self, category: &str) -> bool {
+        self.category().map(|c| Categories.tree().is_a(&c, category)).unwrap_or(false)
+    }
+
+    pub fn has_trait(&self, t: Trait) -> bool {
+        self.data().map(|d| d.traits.contains(&t)).unwrap_or(false)
+    }
+
+    /// slot is where the item is worn, from the target of its category. Only equipable
+    /// items have one.
+    pub fn slot(&self) -> Option<OrganicSlot> {
+        if !self.has_trait(Trait::Equipable) {
+            return None;
+        }
+        Categories.tree().target(&self.category()?)
+    }
+
+    pub fn armor(&self) -> f32 {
+        self.data().and_then(|d| d.armor).unwrap_or(0.) * self.tier().multiplier()
+    }
+
+    /// items only stack when they are the same in every way
+    pub fn stacks_with(&self, o: &Item) -> bool {
+        self == o
+    }
+}
+
+/// ItemStack is a pile of the same item. On its own it's an item lying on the ground.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemStack {
+    pub item: Item,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn new(item: Item, count: u32) -> Self {
+        Self { item, count }
+    }
+
+    pub fn weight(&self) -> f32 {
+        self.item.weight() * self.count as f32
+    }
+
+    /// split takes up to count items off the stack
+    pub fn split(&mut self, count: u32) -> ItemStack {
+        let n = count.min(self.count);
+        self.count -= n;
+        ItemStack::new(self.item.clone(), n)
+    }
+}
+
+/// Storage marks inventories that count as colony storage, workstations take their
+/// ingredients from these and gear is fetched from them
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Storage;
+
+/// Inventory holds stacks of items up to a weight capacity
+#[derive(Debug, Default, Clone)]
+pub struct Inventory {
+    pub capacity: f32,
+    pub stacks: Vec<ItemStack>,
+}
+
+impl Inventory {
+    pub fn new(capacity: f32) -> Self {
+        Self {
+            capacity,
+            stacks: vec![],
+        }
+    }
+
+    pub fn weight(&self) -> f32 {
+        self.stacks.iter().map(|s| s.weight()).sum()
+    }
+
+    pub fn free(&self) -> f32 {
+        (self.capacity - self.weight()).max(0.)
+    }
+
+    pub fn count(&self, id: ItemId) -> u32 {
+        self.stacks.iter().filter(|s| s.item.id == id).map(|s| s.count).sum()
+    }
+
+    /// fits is how many of the item can still be added
+    pub fn fits(&self, item: &Item) -> u32 {
+        let w = item.weight();
+        if w <= 0. {
+            return u32::MAX;
+        }
+        (self.free() / w).floor() as u32
+    }
+
+    /// add puts as much of the stack as fits into the inventory, topping up existing stacks
+    /// before starting new ones. Whatever didn't fit is returned.
+    pub fn add(&mut self, mut stack: ItemStack) -> Option<ItemStack> {
+        let mut n = stack.count.min(self.fits(&stack.item));
+        let max = stack.item.stack_size();
+        for s in self.stacks.iter_mut().filter(|s| s.item.stacks_with(&stack.item)) {
+            if n == 0 {
+                break;
+            }
+            let room = max.saturating_sub(s.count).min(n);
+            s.count += room;
+            stack.count -= room;
+            n -= room;
+        }
+        while n > 0 {
+            let c = n.min(max);
+            self.stacks.push(stack.split(c));
+            n -= c;
+        }
+        if stack.count > 0 {
+            return Some(stack);
+        }
+        None
+    }
+
+    /// remove takes up to count of the item out of the inventory. Items of the same id but
+    /// a different quality don't stack, so only the ones matching the last stack are taken.
+    pub fn remove(&mut self, id: ItemId, count: u32) -> Option<ItemStack> {
+        let item = self.stacks.iter().rev().find(|s| s.item.id == id)?.item.clone();
+        self.remove_item(&item, count)
+    }
+
+    /// remove_item takes up to count of the items that stack with the given one
+    pub fn remove_item(&mut self, item: &Item, count: u32) -> Option<ItemStack> {
+        let mut out: Option<ItemStack> = None;
+        let mut n = count;
+        for s in self.stacks.iter_mut().rev().filter(|s| s.item.stacks_with(item)) {
+            if n == 0 {
+                break;
+            }
+            let taken = s.split(n);
+            n -= taken.count;
+            match &mut out {
+                Some(o) => o.count += taken.count,
+                None => out = Some(taken),
+            }
+        }
+        self.stacks.retain(|s| s.count > 0);
+        out
+    }
+
+    /// transfer moves up to count of the item into the other inventory. Only what fits is
+    /// taken out, so nothing is ever lost. Returns how many were moved.
+    pub fn transfer(&mut self, to: &mut Inventory, id: ItemId, count: u32) -> u32 {
+        let item = match self.stacks.iter().rev().find(|s| s.item.id == id) {
+            Some(s) => s.item.clone(),
+            None => return 0,
+        };
+        let n = count.min(to.fits(&item));
+        match self.remove(id, n) {
+            Some(s) => {
+                let moved = s.count;
+                if let Some(back) = to.add(s) {
+                    // fits said it would fit, but put it back rather than lose it
+                    let returned = back.count;
+                    self.add(back);
+                    return moved - returned;
+                }
+                moved
+            }
+            None => 0,
+        }
+    }
+}
+
+/// refresh_items updates carried, stored, worn and dropped items when item or material
+/// data is reloaded
+fn refresh_items(
+    mut evs: EventReader<DataChanged>,
+    mut inventories: Query<&mut Inventory>,
+    mut worn: Query<&mut Equipment>,
+    mut ground: Query<&mut ItemStack>,
+) {
+    if !evs.iter().any(|e| e.kind == ManifestType::ItemData || e.kind == ManifestType::MaterialData) {
+        return;
+    }
+    for mut inv in inventories.iter_mut() {
+        inv.stacks.iter_mut().for_each(|s| s.item.refresh());
+    }
+    for mut eq in worn.iter_mut() {
+        eq.slots.values_mut().for_each(|i| i.refresh());
+    }
+    for mut s in ground.iter_mut() {
+        s.item.refresh();
+    }
+}
+
+pub trait SpawnItem<'w, 's> {
+    /// spawn_item drops a stack of items on the ground
+    fn spawn_item<'a>(&'a mut self, stack: ItemStack, position: Vec3) -> EntityCommands<'w, 's, 'a>;
+}
+
+impl<'w, 's> SpawnItem<'w, 's> for Commands<'w, 's> {
+    fn spawn_item<'a>(&'a mut self, stack: ItemStack, position: Vec3) -> EntityCommands<'w, 's, 'a> {
+        let nutrition = stack.item.data().and_then(|d| d.nutrition);
+        let mut base = self.spawn();
+        base.insert(stack)
+            .insert(Transform::from_translation(position))
+            .insert(GlobalTransform::identity());
+        if let Some(n) = nutrition {
+            base.insert(Food { nutrition: n });
+        }
+        base
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::world::data::Items;
+
+    // blocks weigh 2 and stack to 10, boots weigh 1 and don't stack
+    fn item(id: &str) -> Item {
+        let items = [("blocks", 2., 10), ("boots", 1., 1)].iter()
+            .map(|(id, weight, stack_size)| {
+                let d = ItemData {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    weight: *weight,
+                    stack_size: *stack_size,
+                    ..Default::default()
+                };
+                (id.to_string(), d)
+            })
+            .collect();
+        Items.replace(items);
+        Item::new(ItemId::of(id).unwrap()).unwrap()
+    }
+
+    fn counts(inv: &Inventory) -> Vec<u32> {
+        inv.stacks.iter().map(|s| s.count).collect()
+    }
+
+    #[test]
+    fn add_tops_up_stacks_before_starting_new_ones() {
+        let mut inv = Inventory::new(100.);
+        assert!(inv.add(ItemStack::new(item("blocks"), 7)).is_none());
+        assert!(inv.add(ItemStack::new(item("blocks"), 5)).is_none());
+        assert_eq!(counts(&inv), vec![10, 2]);
+        assert!(inv.add(ItemStack::new(item("boots"), 2)).is_none());
+        assert_eq!(counts(&inv), vec![10, 2, 1, 1]);
+    }
+
+    #[test]
+    fn add_gives_back_what_doesnt_fit() {
+        let mut inv = Inventory::new(10.);
+        let back = inv.add(ItemStack::new(item("blocks"), 8)).unwrap();
+        assert_eq!(back.count, 3);
+        assert_eq!(inv.count(item("blocks").id), 5);
+        assert_eq!(inv.free(), 0.);
+    }
+
+    #[test]
+    fn other_qualities_are_kept_apart() {
+        let blocks = item("blocks");
+        let mut inv = Inventory::new(100.);
+        inv.add(ItemStack::new(blocks.clone(), 3));
+        inv.add(ItemStack::new(blocks.clone().with_quality(90), 4));
+        assert_eq!(counts(&inv), vec![3, 4]);
+        assert_eq!(inv.count(blocks.id), 7);
+        // only the items matching the last stack are taken
+        let taken = inv.remove(blocks.id, 6).unwrap();
+        assert_eq!((taken.count, taken.item.quality), (4, 90));
+        assert_eq!(counts(&inv), vec![3]);
+        assert_eq!(inv.remove_item(&blocks, 6).unwrap().count, 3);
+        assert!(inv.stacks.is_empty());
+    }
+
+    #[test]
+    fn transfer_only_moves_what_fits() {
+        let blocks = item("blocks");
+        let mut from = Inventory::new(100.);
+        from.add(ItemStack::new(blocks.clone(), 10));
+        let mut to = Inventory::new(7.);
+        assert_eq!(from.transfer(&mut to, blocks.id, 10), 3);
+        assert_eq!((from.count(blocks.id), to.count(blocks.id)), (7, 3));
+        assert_eq!(from.transfer(&mut to, item("boots").id, 1), 0);
+    }
+}
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use bevy::utils::StableHashMap;
+use crate::sim::world::{
+    buildings::Blueprint,
+    data::{ids::ItemId, Cost},
+    item::{Inventory, Storage},
+};
+
+/// System keeps the colony's resource ledger up to date
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Ledger>()
+            .add_system(update_ledger);
+    }
+}
+
+/// Ledger counts the items the colony has in storage by id, and how many of them are
+/// already promised to blueprints
+#[derive(Debug, Default)]
+pub struct Ledger {
+    pub stored: StableHashMap<ItemId, u32>,
+    pub reserved: StableHashMap<ItemId, u32>,
+}
+
+impl Ledger {
+    pub fn stored(&self, id: ItemId) -> u32 {
+        self.stored.get(&id).copied().unwrap_or(0)
+    }
+
+    pub fn reserved(&self, id: ItemId) -> u32 {
+        self.reserved.get(&id).copied().unwrap_or(0)
+    }
+
+    /// available is what's in storage and not yet promised to anything
+    pub fn available(&self, id: ItemId) -> u32 {
+        self.stored(id).saturating_sub(self.reserved(id))
+    }
+
+    pub fn reserve(&mut self, id: ItemId, count: u32) {
+        *self.reserved.entry(id).or_insert(0) += count;
+    }
+
+    /// missing is how many of each cost item the colony is short of
+    pub fn missing(&self, costs: &[Cost]) -> Vec<(ItemId, u32)> {
+        costs.iter()
+            .filter_map(|c| {
+                let short = (c.value.max(0) as u32).saturating_sub(self.available(c.name));
+                if short == 0 {
+                    return None;
+                }
+                Some((c.name, short))
+            })
+            .collect()
+    }
+
+    pub fn can_afford(&self, costs: &[Cost]) -> bool {
+        self.missing(costs).is_empty()
+    }
+}
+
+/// update_ledger recounts storage and the materials blueprints still need
+fn update_ledger(
+    mut ledger: ResMut<Ledger>,
+    stores: Query<&Inventory, With<Storage>>,
+    blueprints: Query<(&Blueprint, &Inventory)>,
+) {
+    let mut stored: StableHashMap<ItemId, u32> = Default::default();
+    for s in stores.iter().flat_map(|inv| inv.stacks.iter()) {
+        *stored.entry(s.item.id).or_insert(0) += s.count;
+    }
+    let mut reserved: StableHashMap<ItemId, u32> = Default::default();
+    for (id, n) in blueprints.iter().flat_map(|(bp, inv)| bp.missing(inv)) {
+        *reserved.entry(id).or_insert(0) += n;
+    }
+    ledger.stored = stored;
+    ledger.reserved = reserved;
+}
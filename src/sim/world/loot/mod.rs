@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use rand::Rng;
+use crate::sim::{
+    generator::{SimRng, WorldOptions},
+    world::{
+        body::{Died, InjuryKind},
+        class::ExperienceModifier,
+        data::{ids::ItemId, DropTable},
+        faction::{Faction, Relations},
+        item::{Item, ItemStack, SpawnItem},
+    },
+};
+
+/// System drops loot where hostiles die
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_system(drop_loot);
+    }
+}
+
+/// roll_drops rolls the table for one death. Counts are scaled by the drop rate and
+/// fractions are rounded up or down at random, weighted by the remainder.
+pub fn roll_drops<R: Rng>(
+    table: &DropTable,
+    cause: Option<InjuryKind>,
+    rank: Option<ExperienceModifier>,
+    rate: f32,
+    rng: &mut R,
+) -> Vec<ItemStack> {
+    let entries: Vec<_> = table.entries.iter()
+        .filter(|e| e.weight > 0)
+        .filter(|e| e.when.as_ref().map(|c| c.holds(cause, rank)).unwrap_or(true))
+        .collect();
+    let total: u32 = entries.iter().map(|e| e.weight).sum();
+    if total == 0 {
+        return vec![];
+    }
+
+    let mut out: Vec<ItemStack> = vec![];
+    for _ in 0..table.rolls {
+        let mut pick = rng.gen_range(0..total);
+        let entry = match entries.iter().find(|e| {
+            if pick < e.weight {
+                return true;
+            }
+            pick -= e.weight;
+            false
+        }) {
+            Some(e) => e,
+            None => continue,
+        };
+        let (lo, hi) = (entry.count[0].min(entry.count[1]), entry.count[0].max(entry.count[1]));
+        let v = rng.gen_range(lo..=hi) as f32 * rate.max(0.);
+        let mut count = v.floor() as u32;
+        if rng.gen::<f32>() < v.fract() {
+            count += 1;
+        }
+        if count == 0 {
+            continue;
+        }
+        let id = match ItemId::of(&entry.item) {
+            Some(id) => id,
+            None => continue,
+        };
+        match out.iter_mut().find(|s| s.item.id == id) {
+            Some(s) => s.count += count,
+            None => {
+                if let Some(i) = Item::new(id) {
+                    out.push(ItemStack::new(i, count));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// drop_loot rolls the drop table of every hostile that died and spawns the items where
+/// it fell
+fn drop_loot(
+    mut commands: Commands,
+    mut rng: ResMut<SimRng>,
+    opts: Option<Res<WorldOptions>>,
+    relations: Res<Relations>,
+    mut deaths: EventReader<Died>,
+) {
+    let rate = opts.map(|o| o.hostile_drop_rate as f32).unwrap_or(1.);
+    for d in deaths.iter() {
+        let hostile = d.faction
+            .map(|f| relations.is_hostile(Faction::Colony, f))
+            .unwrap_or(false);
+        if !hostile {
+            continue;
+        }
+        let table = match d.id.and_then(|id| id.data()).and_then(|ld| ld.drops.clone()) {
+            Some(t) => t,
+            None => continue,
+        };
+        for s in roll_drops(&table, d.cause, d.rank, rate, &mut rng.0) {
+            commands.spawn_item(s, d.position);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use bevy::ecs::system::EntityCommands;
+use bevy::utils::StableHashMap;
+use crate::sim::{
+    jobs::{JobKind, Jobs, NO_JOB},
+    world::{
+        class::{Ability, Class, ExperienceEvent, Progression},
+        data::{MechParts, MechStats},
+        entity::{DerivedAttributes, Living},
+        item::MechSlot,
+    },
+};
+
+/// System boards pilots into mechs and moves piloted mechs along their move jobs
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_system(board_mechs)
+            .add_system(move_mechs)
+            .add_system(carry_pilots);
+    }
+}
+
+// how close a pilot has to be to climb in
+const REACH: f32 = 1.5;
+// how close a mech has to get for a move to be done
+const ARRIVED: f32 = 0.1;
+
+/// Mech is a vehicle assembled from parts, one per slot. It does nothing without a pilot.
+#[derive(Debug, Default, Clone)]
+pub struct Mech {
+    pub name: String,
+    pub parts: StableHashMap<MechSlot, String>,
+    pub pilot: Option<Entity>,
+}
+
+/// Piloting is put on a living while it is inside a mech
+#[derive(Debug, Copy, Clone)]
+pub struct Piloting(pub Entity);
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FitError {
+    UnknownPart(String),
+    WrongSlot { part: String, slot: MechSlot },
+}
+
+impl Mech {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// fit puts the part into its slot, returning the part it replaced
+    pub fn fit(&mut self, part: &str) -> Result<Option<String>, FitError> {
+        let pd = MechParts.get(part).ok_or_else(|| FitError::UnknownPart(part.to_string()))?;
+        Ok(self.parts.insert(pd.slot, pd.id.clone()))
+    }
+
+    /// fit_into is fit, but fails if the part doesn't belong in the given slot
+    pub fn fit_into(&mut self, slot: MechSlot, part: &str) -> Result<Option<String>, FitError> {
+        let pd = MechParts.get(part).ok_or_else(|| FitError::UnknownPart(part.to_string()))?;
+        if pd.slot != slot {
+            return Err(FitError::WrongSlot { part: part.to_string(), slot });
+        }
+        self.fit(part)
+    }
+
+    pub fn remove(&mut self, slot: MechSlot) -> Option<String> {
+        self.parts.remove(&slot)
+    }
+
+    /// stats sums the stats of all the fitted parts
+    pub fn stats(&self) -> MechStats {
+        self.parts.values()
+            .filter_map(|p| MechParts.get(p))
+            .fold(MechStats::default(), |acc, p| acc + p.stats)
+    }
+
+    /// is_operable is true when the mech can move under its own power
+    pub fn is_operable(&self) -> bool {
+        let s = self.stats();
+        self.parts.contains_key(&MechSlot::Processor)
+            && self.parts.contains_key(&MechSlot::Locomotion)
+            && s.power >= 0.
+    }
+}
+
+/// pilot_modifier scales the mech's performance by how good the pilot is, rank bonuses
+/// included
+pub fn pilot_modifier(l: &Living, p: Option<&Progression>) -> f32 {
+    let attrs = match p {
+        Some(p) => l.attrs.with_modifiers(&p.bonuses()),
+        None => l.attrs,
+    };
+    let sync = match p {
+        Some(p) if p.abilities().contains(&Ability::MechSync) => 0.25,
+        _ => 0.,
+    };
+    (0.5 + attrs.piloting() / 10. + sync).clamp(0.5, 1.75)
+}
+
+pub trait SpawnMech<'w, 's> {
+    /// spawn_mech spawns an unpiloted mech with the given parts fitted, unknown parts are
+    /// skipped
+    fn spawn_mech<'a>(&'a mut self, name: &str, parts: &[&str], position: Vec3) -> EntityCommands<'w, 's, 'a>;
+}
+
+impl<'w, 's> SpawnMech<'w, 's> for Commands<'w, 's> {
+    fn spawn_mech<'a>(&'a mut self, name: &str, parts: &[&str], position: Vec3) -> EntityCommands<'w, 's, 'a> {
+        let mut m = Mech::new(name);
+        parts.iter().for_each(|p| {
+            if let Err(e) = m.fit(p) {
+                warn!("can't fit part to {}: {:?}", name, e);
+            }
+        });
+        let mut base = self.spawn();
+        base.insert(m)
+            .insert(Transform::from_translation(position))
+            .insert(GlobalTransform::identity());
+        base
+    }
+}
+
+/// board_mechs puts pilots that reached their mech into it. Only pilots can board.
+fn board_mechs(
+    mut commands: Commands,
+    mut jobs: ResMut<Jobs>,
+    mut pilots: Query<(&mut Living, Option<&Progression>)>,
+    mut mechs: Query<(&mut Mech, &Transform)>,
+) {
+    let boarding: Vec<_> = jobs.list.iter()
+        .filter(|j| j.kind == JobKind::Board)
+        .filter_map(|j| Some((j.id, j.assignee?, j.target?)))
+        .collect();
+    for (id, pe, me) in boarding {
+        let (mut l, prog) = match pilots.get_mut(pe) {
+            Ok(p) => p,
+            Err(_) => {
+                jobs.finish(id);
+                continue;
+            }
+        };
+        let (mut m, t) = match mechs.get_mut(me) {
+            Ok(m) => m,
+            Err(_) => {
+                jobs.finish(id);
+                l.has_job = NO_JOB;
+                continue;
+            }
+        };
+        if prog.map(|p| p.class) != Some(Class::Pilot) || m.pilot.is_some() {
+            jobs.finish(id);
+            l.has_job = NO_JOB;
+            continue;
+        }
+        if t.translation.distance(l.position) > REACH {
+            continue;
+        }
+        m.pilot = Some(pe);
+        commands.entity(pe).insert(Piloting(me));
+        jobs.finish(id);
+        l.has_job = NO_JOB;
+    }
+}
+
+/// move_mechs moves piloted mechs towards their move job, the pilot learns from the trip
+fn move_mechs(
+    time: Res<Time>,
+    mut jobs: ResMut<Jobs>,
+    mut xp: EventWriter<ExperienceEvent>,
+    mut mechs: Query<(Entity, &Mech, &mut Transform)>,
+    pilots: Query<(&Living, Option<&Progression>)>,
+) {
+    let dt = time.delta_seconds();
+    for (e, m, mut t) in mechs.iter_mut() {
+        let job = jobs.list.iter()
+            .find(|j| j.kind == JobKind::Move && j.assignee == Some(e))
+            .and_then(|j| Some((j.id, j.position?)));
+        let (id, target) = match job {
+            Some(j) => j,
+            None => continue,
+        };
+        let (pe, (pilot, prog)) = match m.pilot.and_then(|p| pilots.get(p).ok().map(|l| (p, l))) {
+            Some(p) => p,
+            None => continue,
+        };
+        if !m.is_operable() {
+            continue;
+        }
+        let speed = m.stats().speed * pilot_modifier(pilot, prog);
+        let to = target - t.translation;
+        if to.length() <= ARRIVED {
+            jobs.finish(id);
+            continue;
+        }
+        let step = (speed * dt).min(to.length());
+        t.translation += to.normalize() * step;
+        xp.send(ExperienceEvent {
+            entity: pe,
+            skill: "piloting".to_string(),
+            amount: step,
+        });
+    }
+}
+
+/// carry_pilots keeps pilots where their mech is
+fn carry_pilots(mechs: Query<(&Mech, &Transform)>, mut pilots: Query<&mut Living>) {
+    for (m, t) in mechs.iter() {
+        if let Some(mut l) = m.pilot.and_then(|p| pilots.get_mut(p).ok()) {
+            l.position = t.translation;
+        }
+    }
+}
@@ -5,4 +5,17 @@ pub mod raw;
 pub mod layers;
 pub mod buildings;
 pub mod data;
-pub mod tilemap;
\ No newline at end of file
+pub mod tilemap;
+pub mod needs;
+pub mod body;
+pub mod mech;
+pub mod faction;
+pub mod category;
+pub mod beauty;
+pub mod equipment;
+pub mod fabrication;
+pub mod salvage;
+pub mod hauling;
+pub mod stockpile;
+pub mod ledger;
+pub mod loot;
@@ -0,0 +1,291 @@
+use bevy::prelude::*;
+use crate::sim::{
+    jobs::{JobId, JobKind, Jobs, NO_JOB},
+    world::{
+        data::{assets::DataChanged, Entities, ManifestType, NeedRates, RaceData, Races},
+        entity::{Living, Trait},
+    },
+};
+
+/// System decays the needs of every living and turns critical needs into jobs
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_system(decay_needs)
+            .add_system(refresh_needs)
+            .add_system(prioritize_needs)
+            .add_system(satisfy_needs);
+    }
+}
+
+// the attr names trait modifiers use to change need rates
+pub const NEED_ATTRS: [&str; 3] = ["hunger", "rest", "comfort"];
+pub const NEED_MAX: f32 = 100.;
+/// below this a need job is queued once the living is idle
+pub const NEED_LOW: f32 = 35.;
+/// below this the living drops whatever it is doing to take care of it
+pub const NEED_CRITICAL: f32 = 10.;
+
+// how close a living has to be to use food or a bed
+const REACH: f32 = 1.5;
+
+// the trait changes of a living without any traits
+const UNCHANGED: NeedRates = NeedRates { hunger: 1., rest: 1., comfort: 1. };
+
+const NEED_JOB_PRIORITY: u8 = 8;
+const CRITICAL_JOB_PRIORITY: u8 = 10;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Need {
+    Hunger,
+    Rest,
+    Comfort,
+}
+
+/// Needs are the wants of a living, each goes from 0 (desperate) to NEED_MAX (content)
+#[derive(Debug, Clone)]
+pub struct Needs {
+    pub hunger: f32,
+    pub rest: f32,
+    pub comfort: f32,
+    pub rates: NeedRates,
+    // what the traits multiply the race rates by, kept to apply them to a reloaded race
+    pub multipliers: NeedRates,
+}
+
+impl Default for Needs {
+    fn default() -> Self {
+        Self::new(NeedRates::default())
+    }
+}
+
+impl Needs {
+    pub fn new(rates: NeedRates) -> Self {
+        Self {
+            hunger: NEED_MAX,
+            rest: NEED_MAX,
+            comfort: NEED_MAX,
+            rates,
+            multipliers: UNCHANGED,
+        }
+    }
+
+    /// for_living builds the needs from the race rates, modified by any trait with a
+    /// `hunger`, `rest` or `comfort` attr. Trait values are a percent change in the rate.
+    pub fn for_living(race: &RaceData, traits: &[Trait]) -> Option<Self> {
+        let mut n = Self::new(race.needs?);
+        traits.iter()
+            .flat_map(|t| t.modifiers.iter())
+            .for_each(|m| {
+                let pct = 1. + m.value as f32 / 100.;
+                match m.attr.as_deref() {
+                    Some("hunger") => n.multipliers.hunger *= pct,
+                    Some("rest") => n.multipliers.rest *= pct,
+                    Some("comfort") => n.multipliers.comfort *= pct,
+                    _ => (),
+                }
+            });
+        n.set_rates(n.rates);
+        Some(n)
+    }
+
+    /// set_rates sets the rates from the race rates, with the traits applied
+    pub fn set_rates(&mut self, race: NeedRates) {
+        self.rates = NeedRates {
+            hunger: (race.hunger * self.multipliers.hunger).max(0.),
+            rest: (race.rest * self.multipliers.rest).max(0.),
+            comfort: (race.comfort * self.multipliers.comfort).max(0.),
+        };
+    }
+
+    pub fn decay(&mut self, dt: f32) {
+        self.hunger = (self.hunger - self.rates.hunger * dt).max(0.);
+        self.rest = (self.rest - self.rates.rest * dt).max(0.);
+        self.comfort = (self.comfort - self.rates.comfort * dt).max(0.);
+    }
+
+    /// urgent returns the needs below the given threshold, the lowest first
+    pub fn urgent(&self, threshold: f32) -> Vec<Need> {
+        let mut needs: Vec<(Need, f32)> = [
+            (Need::Hunger, self.hunger),
+            (Need::Rest, self.rest),
+            (Need::Comfort, self.comfort),
+        ].iter()
+            .copied()
+            .filter(|(_, v)| *v < threshold)
+            .collect();
+        needs.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        needs.into_iter().map(|(n, _)| n).collect()
+    }
+}
+
+/// Food can be eaten to restore hunger
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Food {
+    pub nutrition: f32,
+}
+
+/// Bed can be slept in to restore rest, the comfort is restored per second spent in it
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Bed {
+    pub comfort: f32,
+}
+
+fn decay_needs(time: Res<Time>, mut q: Query<&mut Needs>) {
+    let dt = time.delta_seconds();
+    for mut n in q.iter_mut() {
+        n.decay(dt);
+    }
+}
+
+/// refresh_needs applies reloaded race rates to the livings of the race. Races that no
+/// longer have needs leave the rates as they were.
+fn refresh_needs(mut evs: EventReader<DataChanged>, mut q: Query<(&Living, &mut Needs)>) {
+    if !evs.iter().any(|e| e.kind == ManifestType::RaceData || e.kind == ManifestType::LivingData) {
+        return;
+    }
+    for (l, mut n) in q.iter_mut() {
+        let rates = Entities.get(&l.id)
+            .and_then(|ld| Races.get(&ld.race))
+            .and_then(|r| r.needs);
+        if let Some(r) = rates {
+            n.set_rates(r);
+        }
+    }
+}
+
+/// prioritize_needs creates eat, sleep and relax jobs. Critical needs interrupt the
+/// current job, low needs wait until the living is idle. A need that can't be taken care
+/// of right now gives way to the next most urgent one.
+fn prioritize_needs(
+    mut jobs: ResMut<Jobs>,
+    mut q: Query<(Entity, &mut Living, &Needs)>,
+    food: Query<(Entity, &Transform), With<Food>>,
+    beds: Query<(Entity, &Transform, &Bed)>,
+) {
+    for (e, mut l, n) in q.iter_mut() {
+        let current = jobs.get(l.has_job).cloned();
+        if let Some(j) = &current {
+            if j.is_need() {
+                continue;
+            }
+        }
+
+        let job_for = |need: Need| match need {
+            Need::Hunger => nearest(l.position, food.iter())
+                .map(|t| (JobKind::Eat, Some(t))),
+            // sleeping works anywhere, a bed is only nicer
+            Need::Rest => Some((JobKind::Sleep, nearest(l.position, beds.iter().map(|(e, tf, _)| (e, tf))))),
+            // only a bed that is comfortable makes a living comfortable
+            Need::Comfort => nearest(l.position, beds.iter().filter(|(_, _, b)| b.comfort > 0.).map(|(e, tf, _)| (e, tf)))
+                .map(|t| (JobKind::Relax, Some(t))),
+        };
+        let critical = n.urgent(NEED_CRITICAL).into_iter()
+            .find_map(job_for)
+            .map(|j| (j, CRITICAL_JOB_PRIORITY));
+        let low = || match current {
+            None => n.urgent(NEED_LOW).into_iter()
+                .find_map(job_for)
+                .map(|j| (j, NEED_JOB_PRIORITY)),
+            Some(_) => None,
+        };
+        let ((kind, target), priority) = match critical.or_else(low) {
+            Some(j) => j,
+            None => continue,
+        };
+
+        if current.is_some() {
+            jobs.interrupt(l.has_job);
+        }
+        let id = jobs.push(kind, priority, target);
+        jobs.assign(id, e);
+        l.has_job = id;
+    }
+}
+
+/// satisfy_needs works the need jobs of each living. Eating consumes the food in one go,
+/// sleeping restores rest until the living is fully rested and relaxing restores comfort
+/// until it is fully comfortable.
+fn satisfy_needs(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut jobs: ResMut<Jobs>,
+    mut q: Query<(&mut Living, &mut Needs)>,
+    food: Query<(&Food, &Transform)>,
+    beds: Query<(&Bed, &Transform)>,
+) {
+    let dt = time.delta_seconds();
+    for (mut l, mut n) in q.iter_mut() {
+        let j = match jobs.get(l.has_job) {
+            Some(j) => j.clone(),
+            None => continue,
+        };
+        match j.kind {
+            JobKind::Eat => {
+                let target = j.target.and_then(|t| food.get(t).ok().map(|f| (t, f)));
+                match target {
+                    Some((t, (f, tf))) => {
+                        if tf.translation.distance(l.position) > REACH {
+                            continue;
+                        }
+                        n.hunger = (n.hunger + f.nutrition).min(NEED_MAX);
+                        commands.entity(t).despawn_recursive();
+                    }
+                    // someone got to it first
+                    None => (),
+                }
+                finish(&mut jobs, &mut l, j.id);
+            }
+            JobKind::Sleep => {
+                let bed = j.target.and_then(|t| beds.get(t).ok());
+                let comfort = match bed {
+                    Some((b, tf)) => {
+                        if tf.translation.distance(l.position) > REACH {
+                            continue;
+                        }
+                        b.comfort
+                    }
+                    // sleeping on the ground is restful, but not comfortable
+                    None => 0.,
+                };
+                n.rest = (n.rest + n.rates.rest * 20. * dt).min(NEED_MAX);
+                n.comfort = (n.comfort + comfort * dt).min(NEED_MAX);
+                if n.rest >= NEED_MAX {
+                    finish(&mut jobs, &mut l, j.id);
+                }
+            }
+            JobKind::Relax => {
+                let bed = j.target.and_then(|t| beds.get(t).ok());
+                match bed {
+                    Some((b, tf)) => {
+                        if tf.translation.distance(l.position) > REACH {
+                            continue;
+                        }
+                        n.comfort = (n.comfort + b.comfort * dt).min(NEED_MAX);
+                        if n.comfort >= NEED_MAX {
+                            finish(&mut jobs, &mut l, j.id);
+                        }
+                    }
+                    // the bed is gone, there's nothing to relax in
+                    None => finish(&mut jobs, &mut l, j.id),
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn finish(jobs: &mut Jobs, l: &mut Living, id: JobId) {
+    jobs.finish(id);
+    l.has_job = NO_JOB;
+}
+
+fn nearest<'a, I>(from: Vec3, it: I) -> Option<Entity>
+    where I: Iterator<Item = (Entity, &'a Transform)> {
+    it.min_by(|a, b| {
+        let da = a.1.translation.distance_squared(from);
+        let db = b.1.translation.distance_squared(from);
+        da.partial_cmp(&db).unwrap()
+    }).map(|(e, _)| e)
+}
@@ -1,13 +1,101 @@
 // use bevy_asset_loader::{AssetLoader, AssetCollection};
+use rand::Rng;
+use crate::sim::world::data::{MaterialAttr, MaterialData, Materials};
 
 struct AssetDB {
 }
 
-pub enum Materials {
-    Wood,
-    // Stone,
-    // Aluminum,
-    // Steel,
-    // Iron,
-    // Titanium
-}
\ No newline at end of file
+// rolls are rounded to this many percent, so most things made of the same material come
+// out the same and stack
+const ROLL_STEP: i32 = 5;
+
+/// MaterialRoll is what a thing ended up with when it was made from a material. Every
+/// value is a percent change to the matching stat.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MaterialRoll {
+    pub material: String,
+    pub damage: i32,
+    pub durability: i32,
+    pub weight: i32,
+}
+
+impl MaterialRoll {
+    /// roll rolls every property range of the material, rounded to ROLL_STEP
+    pub fn roll<R: Rng>(md: &MaterialData, rng: &mut R) -> Self {
+        let mut out = MaterialRoll {
+            material: md.id.clone(),
+            ..Default::default()
+        };
+        md.properties.iter().for_each(|p| {
+            let [a, b] = p.value;
+            let v = rng.gen_range(a.min(b)..=a.max(b));
+            match p.attr {
+                MaterialAttr::Damage => out.damage += v,
+                MaterialAttr::Durability => out.durability += v,
+                MaterialAttr::Weight => out.weight += v,
+            }
+        });
+        let step = |v: i32| (v as f32 / ROLL_STEP as f32).round() as i32 * ROLL_STEP;
+        out.damage = step(out.damage);
+        out.durability = step(out.durability);
+        out.weight = step(out.weight);
+        out
+    }
+
+    /// roll_id looks the material up by id before rolling it
+    pub fn roll_id<R: Rng>(id: &str, rng: &mut R) -> Option<Self> {
+        Materials.get(id).map(|md| Self::roll(&md, rng))
+    }
+
+    pub fn name(&self) -> String {
+        Materials.get(&self.material).map(|m| m.name.clone()).unwrap_or_default()
+    }
+
+    /// apply scales a value by a percent change
+    pub fn apply(v: f32, pct: i32) -> f32 {
+        (v * (1. + pct as f32 / 100.)).max(0.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{generator::SimRng, world::data::MaterialProperty};
+
+    fn material(properties: &[(MaterialAttr, [i32; 2])]) -> MaterialData {
+        MaterialData {
+            name: "Biosteel".to_string(),
+            id: "biosteel".to_string(),
+            properties: properties.iter().map(|(attr, value)| MaterialProperty { attr: *attr, value: *value }).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rolls_are_rounded_to_the_step() {
+        let md = material(&[
+            (MaterialAttr::Damage, [-13, 37]),
+            (MaterialAttr::Durability, [4, 1]),
+            (MaterialAttr::Weight, [6, 9]),
+        ]);
+        let mut rng = SimRng::default();
+        for _ in 0..64 {
+            let r = MaterialRoll::roll(&md, &mut rng.0);
+            for v in [r.damage, r.durability, r.weight] {
+                assert_eq!(v % ROLL_STEP, 0);
+            }
+            assert!((-15..=35).contains(&r.damage));
+            assert!([0, 5].contains(&r.durability));
+            assert!([5, 10].contains(&r.weight));
+        }
+    }
+
+    #[test]
+    fn close_rolls_come_out_the_same() {
+        let md = material(&[(MaterialAttr::Damage, [11, 12]), (MaterialAttr::Damage, [-1, 0])]);
+        let mut rng = SimRng::default();
+        let first = MaterialRoll::roll(&md, &mut rng.0);
+        assert_eq!(first.damage, 10);
+        assert!((0..16).all(|_| MaterialRoll::roll(&md, &mut rng.0) == first));
+    }
+}
@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use rand::Rng;
+use crate::sim::{
+    generator::SimRng,
+    jobs::{JobKind, Jobs, NO_JOB},
+    world::{
+        body::Body,
+        buildings::Building,
+        class::{ExperienceEvent, Progression},
+        data::{ids::ItemId, Recipes},
+        equipment::Equipment,
+        entity::Living,
+        fabrication::{BillTask, Workstation, STORAGE_RANGE},
+        item::{Inventory, Item, ItemStack, SpawnItem, Storage, CONDITION_MAX, QUALITY_MAX},
+    },
+};
+
+/// System turns salvage bills into salvage jobs and works them
+pub struct System;
+
+impl Plugin for System {
+    fn build(&self, app: &mut App) {
+        app.add_system(plan_salvage)
+            .add_system(work_salvage);
+    }
+}
+
+/// the capability a workstation needs to take things apart
+pub const SALVAGE_CAP: &str = "disassembly";
+pub const SALVAGE_SKILL: &str = "disassembly";
+
+// how close a worker has to be to use the workstation
+const REACH: f32 = 1.5;
+const SALVAGE_PRIORITY: u8 = 4;
+// anyone less skilled breaks more than they get back
+const SALVAGE_MIN_SKILL: f32 = 1.;
+// seconds of work to take apart anything, for a worker with a skill of 1
+const SALVAGE_TIME: f32 = 20.;
+// the fraction of materials an unskilled worker gets back from a pristine, normal item
+const YIELD_BASE: f32 = 0.4;
+const YIELD_PER_SKILL: f32 = 0.03;
+const YIELD_MAX: f32 = 0.9;
+
+/// salvage_rule is what one of the item breaks down into at full yield. Items without
+/// their own rule are reversed from the first recipe that makes them, only the inputs
+/// that name an item count. Items that aren't loaded are left out.
+pub fn salvage_rule(id: ItemId) -> Vec<(ItemId, f32)> {
+    if let Some(s) = id.data().and_then(|d| d.salvage.clone()) {
+        return s.iter().filter_map(|o| Some((ItemId::of(&o.item)?, o.count as f32))).collect();
+    }
+    let name = match id.name() {
+        Some(n) => n,
+        None => return vec![],
+    };
+    let recipe = Recipes.values().into_iter().find_map(|r| {
+        let made = r.outputs.iter().find(|o| o.item == name && o.count > 0)?.count;
+        Some((r, made))
+    });
+    match recipe {
+        Some((r, made)) => r.inputs.iter()
+            .filter_map(|i| Some((ItemId::of(i.item.as_ref()?)?, i.count as f32 / made as f32)))
+            .collect(),
+        None => vec![],
+    }
+}
+
+pub fn can_salvage(i: &Item) -> bool {
+    !salvage_rule(i.id).is_empty()
+}
+
+/// salvage_fraction is how much of the materials come back. Skill raises it, wear and
+/// poor quality lower it.
+pub fn salvage_fraction(i: &Item, skill: f32) -> f32 {
+    let worker = (YIELD_BASE + skill * YIELD_PER_SKILL).min(YIELD_MAX);
+    let condition = i.condition.clamp(0, CONDITION_MAX) as f32 / CONDITION_MAX as f32;
+    let quality = 0.75 + i.quality.clamp(0, QUALITY_MAX) as f32 / (QUALITY_MAX as f32 * 2.);
+    (worker * condition * quality).clamp(0., 1.)
+}
+
+/// salvage_yield rolls what comes out of taking the item apart. Fractions are rounded up
+/// or down at random, weighted by the remainder.
+pub fn salvage_yield<R: Rng>(i: &Item, skill: f32, rng: &mut R) -> Vec<ItemStack> {
+    let frac = salvage_fraction(i, skill);
+    salvage_rule(i.id).into_iter()
+        .filter_map(|(id, n)| {
+            let v = n * frac;
+            let mut count = v.floor() as u32;
+            if rng.gen::<f32>() < v.fract() {
+                count += 1;
+            }
+            if count == 0 {
+                return None;
+            }
+            Some(ItemStack::new(Item::new(id)?, count))
+        })
+        .collect()
+}
+
+/// plan_salvage queues a salvage job for idle workstations that can take things apart
+/// and have something to salvage in nearby storage
+fn plan_salvage(
+    mut jobs: ResMut<Jobs>,
+    mut stations: Query<(Entity, &Building, &mut Workstation, &Transform)>,
+    storage: Query<(&Inventory, &Transform), With<Storage>>,
+) {
+    for (e, b, mut ws, t) in stations.iter_mut() {
+        if ws.job != NO_JOB {
+            continue;
+        }
+        if b.data().and_then(|d| d.cap_level(SALVAGE_CAP)).is_none() {
+            continue;
+        }
+        let nearby: Vec<&Inventory> = storage.iter()
+            .filter(|(_, st)| st.translation.distance(t.translation) <= STORAGE_RANGE)
+            .map(|(i, _)| i)
+            .collect();
+        let next = ws.bills.iter().enumerate().find_map(|(idx, bill)| {
+            let category = match &bill.task {
+                BillTask::Salvage(c) => c,
+                _ => return None,
+            };
+            if !bill.is_active(0) {
+                return None;
+            }
+            nearby.iter()
+                .flat_map(|inv| inv.stacks.iter())
+                .map(|s| &s.item)
+                .find(|i| {
+                    can_salvage(i) && category.as_ref().map(|c| i.is_a(c)).unwrap_or(true)
+                })
+                .map(|i| (idx, i.id))
+        });
+        if let Some((idx, id)) = next {
+            ws.job = jobs.push_item(JobKind::Salvage, SALVAGE_PRIORITY, e, id, 1);
+            jobs.require(ws.job, SALVAGE_SKILL, SALVAGE_MIN_SKILL);
+            ws.bill = Some(idx);
+        }
+    }
+}
+
+/// work_salvage takes the item out of storage when work starts and drops the materials
+/// next to the workstation when it's done
+fn work_salvage(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut rng: ResMut<SimRng>,
+    mut jobs: ResMut<Jobs>,
+    mut xp: EventWriter<ExperienceEvent>,
+    mut livings: Query<(Entity, &mut Living, Option<&Body>, Option<&Progression>, Option<&Equipment>)>,
+    mut stations: Query<(&mut Workstation, &Transform)>,
+    mut storage: Query<(&mut Inventory, &Transform), With<Storage>>,
+) {
+    let dt = time.delta_seconds();
+    for (e, mut l, body, p, eq) in livings.iter_mut() {
+        let j = match jobs.get(l.has_job) {
+            Some(j) if j.kind == JobKind::Salvage => j.clone(),
+            _ => continue,
+        };
+        let (mut ws, t) = match j.target.and_then(|t| stations.get_mut(t).ok()) {
+            Some(s) => s,
+            None => {
+                jobs.finish(j.id);
+                l.has_job = NO_JOB;
+                continue;
+            }
+        };
+        if t.translation.distance(l.position) > REACH {
+            continue;
+        }
+
+        if !ws.started {
+            let taken = j.item.and_then(|id| {
+                storage.iter_mut()
+                    .filter(|(_, st)| st.translation.distance(t.translation) <= STORAGE_RANGE)
+                    .find_map(|(mut inv, _)| inv.remove(id, 1))
+            });
+            match taken {
+                Some(s) => {
+                    ws.salvaging = Some(s.item);
+                    ws.started = true;
+                }
+                None => {
+                    jobs.finish(j.id);
+                    ws.reset();
+                    l.has_job = NO_JOB;
+                    continue;
+                }
+            }
+        }
+
+        let skill = l.attributes(p, eq).skill(SALVAGE_SKILL);
+        let speed = skill.max(1.) * body.map(|b| b.manipulation()).unwrap_or(1.);
+        ws.progress += speed * dt;
+        if ws.progress < SALVAGE_TIME {
+            continue;
+        }
+
+        if let Some(item) = ws.salvaging.take() {
+            for s in salvage_yield(&item, skill, &mut rng.0) {
+                commands.spawn_item(s, t.translation);
+            }
+        }
+        xp.send(ExperienceEvent {
+            entity: e,
+            skill: SALVAGE_SKILL.to_string(),
+            amount: SALVAGE_TIME,
+        });
+        let idx = ws.bill;
+        if let Some(bill) = idx.and_then(|i| ws.bills.get_mut(i)) {
+            bill.done += 1;
+        }
+        jobs.finish(j.id);
+        ws.reset();
+        l.has_job = NO_JOB;
+    }
+}
@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use bevy::ecs::system::EntityCommands;
+use crate::sim::world::{
+    data::ids::MaterialId,
+    item::{Inventory, Item, Storage},
+};
+
+// the weight of items one tile of a stockpile holds
+pub const CELL_CAPACITY: f32 = 50.;
+// storage buildings take anything, at the priority a fresh stockpile gets
+pub const DEFAULT_PRIORITY: u8 = 3;
+
+/// StockpileFilter decides which items a stockpile takes. Empty lists take anything.
+#[derive(Debug, Default, Clone)]
+pub struct StockpileFilter {
+    pub categories: Vec<String>,
+    pub materials: Vec<MaterialId>,
+    pub min_quality: i32,
+}
+
+impl StockpileFilter {
+    pub fn accepts(&self, i: &Item) -> bool {
+        if i.quality < self.min_quality {
+            return false;
+        }
+        if !self.categories.is_empty() && !self.categories.iter().any(|c| i.is_a(c)) {
+            return false;
+        }
+        if self.materials.is_empty() {
+            return true;
+        }
+        match i.material.as_ref().and_then(|m| MaterialId::of(&m.material)) {
+            Some(id) => self.materials.contains(&id),
+            None => false,
+        }
+    }
+}
+
+/// Stockpile is a zone of tiles where colonists put items down. Items go to the
+/// highest priority stockpile that accepts them.
+#[derive(Debug, Clone)]
+pub struct Stockpile {
+    pub name: String,
+    pub cells: Vec<IVec2>,
+    pub filter: StockpileFilter,
+    pub priority: u8,
+}
+
+impl Stockpile {
+    pub fn new(name: &str, cells: Vec<IVec2>) -> Self {
+        Self {
+            name: name.to_string(),
+            cells,
+            filter: StockpileFilter::default(),
+            priority: DEFAULT_PRIORITY,
+        }
+    }
+
+    pub fn capacity(&self) -> f32 {
+        self.cells.len() as f32 * CELL_CAPACITY
+    }
+
+    /// center is the middle of the zone, where items are carried to
+    pub fn center(&self) -> Vec3 {
+        if self.cells.is_empty() {
+            return Vec3::ZERO;
+        }
+        let sum = self.cells.iter().fold(Vec3::ZERO, |acc, c| acc + tile_center(*c));
+        sum / self.cells.len() as f32
+    }
+}
+
+pub fn tile_of(pos: Vec3) -> IVec2 {
+    IVec2::new(pos.x.floor() as i32, pos.z.floor() as i32)
+}
+
+pub fn tile_center(tile: IVec2) -> Vec3 {
+    Vec3::new(tile.x as f32 + 0.5, 0., tile.y as f32 + 0.5)
+}
+
+pub trait DesignateStockpile<'w, 's> {
+    /// designate_stockpile creates a stockpile zone, its inventory grows with the tiles
+    fn designate_stockpile<'a>(&'a mut self, s: Stockpile) -> EntityCommands<'w, 's, 'a>;
+}
+
+impl<'w, 's> DesignateStockpile<'w, 's> for Commands<'w, 's> {
+    fn designate_stockpile<'a>(&'a mut self, s: Stockpile) -> EntityCommands<'w, 's, 'a> {
+        let inv = Inventory::new(s.capacity());
+        let position = s.center();
+        let mut base = self.spawn();
+        base.insert(s)
+            .insert(inv)
+            .insert(Storage)
+            .insert(Transform::from_translation(position))
+            .insert(GlobalTransform::identity());
+        base
+    }
+}
@@ -2,12 +2,19 @@ use crate::{
     StartupData,
     AppState,
     TickCount,
+    loading::{LoadingProgress, LoadingState},
     ui::VisibleUI,
     input::InteractionContext,
     sim::{
         world::{
-            data::Buildings,
-            entity::Living
+            data::{
+                locale::{self, tr, tr_with},
+                ids,
+                Buildings,
+                ManifestReport,
+            },
+            entity::{Living, Profile},
+            item::Inventory,
         }
     }
 };
@@ -21,71 +28,153 @@ use bevy::prelude::*;
 use std::ops::RangeInclusive;
 
 pub fn main_menu(egui_context: ResMut<EguiContext>, tc: Res<TickCount>, mut state: ResMut<State<AppState>>) {
-    let w = egui::Window::new("Mechanomancer")
+    let w = egui::Window::new(tr("menu.title"))
         .anchor(Align2::CENTER_CENTER, [0., 0.]);
 
     w.show(egui_context.ctx(), |ui| {
         ui.label(tc.0.to_string());
-        let ng = ui.button("New Game");
-        let lg = ui.button("Load Game");
-        let stt = ui.button("Settings");
-        let x = ui.button("Exit");
+        let ng = ui.button(tr("menu.new_game"));
+        let lg = ui.button(tr("menu.load_game"));
+        let stt = ui.button(tr("menu.settings"));
+        let x = ui.button(tr("menu.exit"));
 
         if ng.clicked() {
             state.set(AppState::NewGame).unwrap()
         }
+        if stt.clicked() {
+            state.set(AppState::Settings).unwrap()
+        }
         if x.clicked() {
             std::process::exit(0);
         }
     });
 }
 
-pub fn new_game(egui_context: ResMut<EguiContext>, mut ngs: ResMut<StartupData>, mut state: ResMut<State<AppState>>) {
-    bevy_egui::egui::Window::new("New Game")
+/// data_errors lists everything wrong with the game data instead of starting the game
+pub fn data_errors(egui_context: ResMut<EguiContext>, report: Res<ManifestReport>) {
+    egui::Window::new(tr("data_errors.title"))
+        .anchor(Align2::CENTER_CENTER, [0., 0.])
+        .show(egui_context.ctx(), |ui| {
+            ui.label(tr_with("data_errors.count", &[("count", &report.errors.len().to_string())]));
+            egui::ScrollArea::vertical().max_height(400.).show(ui, |ui| {
+                for e in report.errors.iter() {
+                    ui.label(e.to_string());
+                }
+            });
+            if ui.button(tr("menu.exit")).clicked() {
+                std::process::exit(1);
+            }
+        });
+}
+
+/// loading shows how far the assets got and starts the game once they're done. Failed
+/// assets are listed and the player decides whether to go on without them.
+pub fn loading(
+    egui_context: ResMut<EguiContext>,
+    progress: Res<LoadingProgress>,
+    loading: Res<State<LoadingState>>,
+    mut state: ResMut<State<AppState>>,
+) {
+    let done = *loading.current() == LoadingState::Done;
+    if done && progress.failed.is_empty() {
+        state.set(AppState::InGame).unwrap();
+        return;
+    }
+    egui::Window::new(tr("loading.title"))
+        .anchor(Align2::CENTER_CENTER, [0., 0.])
+        .show(egui_context.ctx(), |ui| {
+            egui::ProgressBar::new(progress.fraction())
+                .text(format!("{}/{}", progress.finished, progress.total))
+                .ui(ui);
+            if let Some(f) = &progress.current {
+                ui.label(f);
+            }
+            if progress.failed.is_empty() {
+                return;
+            }
+            ui.label(tr_with("loading.failed", &[("count", &progress.failed.len().to_string())]));
+            egui::ScrollArea::vertical().max_height(200.).show(ui, |ui| {
+                for f in progress.failed.iter() {
+                    ui.label(f);
+                }
+            });
+            if done && ui.button(tr("loading.continue")).clicked() {
+                state.set(AppState::InGame).unwrap();
+            }
+        });
+}
+
+/// settings changes options that apply to every game, for now the language
+pub fn settings(egui_context: ResMut<EguiContext>, mut state: ResMut<State<AppState>>) {
+    egui::Window::new(tr("menu.settings"))
+        .anchor(Align2::CENTER_CENTER, [0., 0.])
+        .show(egui_context.ctx(), |ui| {
+            let current = locale::language();
+            ui.horizontal(|ui| {
+                ui.label(tr("settings.language"));
+                egui::ComboBox::from_id_source("language")
+                    .selected_text(locale::language_name(&current))
+                    .show_ui(ui, |ui| {
+                        for lang in locale::languages() {
+                            if ui.selectable_label(lang == current, locale::language_name(&lang)).clicked() {
+                                locale::set_language(&lang);
+                            }
+                        }
+                    });
+            });
+            ui.separator();
+            if ui.button(tr("menu.back")).clicked() {
+                state.set(AppState::MainMenu).unwrap();
+            }
+        });
+}
+
+pub fn new_game(mut commands: Commands, egui_context: ResMut<EguiContext>, mut ngs: ResMut<StartupData>, mut state: ResMut<State<AppState>>) {
+    bevy_egui::egui::Window::new(tr("menu.new_game"))
         .anchor(Align2::CENTER_CENTER, [0., 0.])
         .show(egui_context.ctx(), |ui| {
             ui.group(|ui| {
                 ui.vertical(|ui| {
                     ui.horizontal(|ui| {
                         ui.columns(2, |ui| {
-                            ui[0].label("Seed");
+                            ui[0].label(tr("new_game.seed"));
                             ui[1].text_edit_singleline(&mut ngs.opts.seed);
                         });
                     });
                     let mo = &mut ngs.opts;
-                    ui.collapsing("Environment",|ui| {
+                    ui.collapsing(tr("new_game.environment"),|ui| {
                         for (n, p, mn, mx) in [
-                            ("Temperature", &mut mo.temp_avg, -32.0, 32.0),
-                            ("Resource Richness", &mut mo.rsrc_rich, -50.0, 300.0),
-                            ("Resource Abundance", &mut mo.rssc_abund, -50.0, 300.0),
-                            ("Water", &mut mo.water, -100.0, 300.0),
-                            ("Flatness", &mut mo.flatness, 0.0, 100.0),
-                            ("Vegetation", &mut mo.vegetation, -100.0, 300.0)
+                            ("world.temperature", &mut mo.temp_avg, -32.0, 32.0),
+                            ("world.resource_richness", &mut mo.rsrc_rich, -50.0, 300.0),
+                            ("world.resource_abundance", &mut mo.rssc_abund, -50.0, 300.0),
+                            ("world.water", &mut mo.water, -100.0, 300.0),
+                            ("world.flatness", &mut mo.flatness, 0.0, 100.0),
+                            ("world.vegetation", &mut mo.vegetation, -100.0, 300.0)
                         ] {
                             ui.horizontal(|ui| {
                                 ui.columns(2, |ui| {
-                                    ui[0].label(n);
+                                    ui[0].label(tr(n));
                                     egui::Slider::new(p, RangeInclusive::new(mn, mx)).ui(&mut ui[1]);
                                 });
                             });
                         }
                     });
 
-                    ui.collapsing("Hostility",|ui| {
+                    ui.collapsing(tr("new_game.hostility"),|ui| {
                         for (n, p, mn, mx, dsc) in [
-                            ("Proclensity", &mut mo.proclensity, -100.0, 100.0, "How good or evil a location is"),
-                            ("Hostile Density", &mut mo.hostile_density, 0.0, 600.0, "Density of hostiles"),
-                            ("Hostile Evolution", &mut mo.hostile_evolution, 0.0, 3.0, "How frequently hostiles evolve"),
-                            ("Hostile Intelligence", &mut mo.hostile_intelligence, 1.0, 3.0, "How smart hostiles will fight"),
-                            ("Hostile Drop Rate", &mut mo.hostile_drop_rate, 1.0, 4.0, "The amount of loot hostiles drop"),
-                            ("Darkness Density", &mut mo.dark_density, 0.1, 4.0, "How dense the darkness is"),
-                            ("Darkness Spread", &mut mo.dark_spread, 0.1, 4.0, "How quickly the darkness will spread"),
-                            ("Pollution Spread", &mut mo.pollution_spread, 0.1, 4.0, "How much your pollution feeds the darkness"),
-                            ("Heat Spread", &mut mo.heat_spread, 0.1, 4.0, "How much your head feeds the darkness")
+                            ("world.proclensity", &mut mo.proclensity, -100.0, 100.0, "world.proclensity.help"),
+                            ("world.hostile_density", &mut mo.hostile_density, 0.0, 600.0, "world.hostile_density.help"),
+                            ("world.hostile_evolution", &mut mo.hostile_evolution, 0.0, 3.0, "world.hostile_evolution.help"),
+                            ("world.hostile_intelligence", &mut mo.hostile_intelligence, 1.0, 3.0, "world.hostile_intelligence.help"),
+                            ("world.hostile_drop_rate", &mut mo.hostile_drop_rate, 1.0, 4.0, "world.hostile_drop_rate.help"),
+                            ("world.darkness_density", &mut mo.dark_density, 0.1, 4.0, "world.darkness_density.help"),
+                            ("world.darkness_spread", &mut mo.dark_spread, 0.1, 4.0, "world.darkness_spread.help"),
+                            ("world.pollution_spread", &mut mo.pollution_spread, 0.1, 4.0, "world.pollution_spread.help"),
+                            ("world.heat_spread", &mut mo.heat_spread, 0.1, 4.0, "world.heat_spread.help")
                         ] {
                             let h = ui.horizontal(|ui| {
                                 let cs = ui.columns(2, |ui| {
-                                    ui[0].label(n);
+                                    ui[0].label(tr(n)).on_hover_text(tr(dsc));
                                     egui::Slider::new(p, RangeInclusive::new(mn, mx)).ui(&mut ui[1]);
                                 });
                             });
@@ -94,21 +183,23 @@ pub fn new_game(egui_context: ResMut<EguiContext>, mut ngs: ResMut<StartupData>,
                 });
             }); // world stats
             ui.group(|ui| {
-                ui.heading("Character");
+                ui.heading(tr("new_game.character"));
             }); // character stats
 
             // forward, back
             ui.separator();
             TopBottomPanel::bottom("ng_ctrl").show_inside(ui, |ui| {
                 ui.horizontal(|ui| {
-                    let back = ui.button("Back");
-                    let start = ui.button("Start");
+                    let back = ui.button(tr("menu.back"));
+                    let start = ui.button(tr("new_game.start"));
                     if back.clicked() {
                         state.set(AppState::MainMenu).unwrap();
                     }
                     // TODO: verification
                     if start.clicked() {
-                        state.set(AppState::InGame).unwrap();
+                        // the sim rolls everything from these from now on
+                        commands.insert_resource(ngs.opts.clone());
+                        state.set(AppState::Loading).unwrap();
                     }
                 });
             }); // bottom bar
@@ -120,27 +211,29 @@ pub fn in_game(egui_context: ResMut<EguiContext>,
                // mut ed: ResMut<EntityData>,
                mut q: Query<(
                    Entity,
-                   Option<&mut VisibleUI>, Option<&Living>)
-               >) {
+                   Option<&mut VisibleUI>, Option<&Living>, Option<&Profile>)
+               >,
+               inventories: Query<(Entity, &Living, Option<&Profile>, &Inventory)>) {
     // draw all visible ui components
-    q.for_each_mut(|(e, o, l)| {
+    q.for_each_mut(|(e, o, l, p)| {
         match o {
             Some(mut o) => {
-                o.draw(e, l.unwrap(), egui_context.ctx())
+                o.draw(e, l.unwrap(), p, egui_context.ctx())
             },
             None => (),
         }
     });
     if g.build_mode {
-        // todo: build this from data table
-        let w = bevy_egui::egui::Window::new("Build")
+        let mut picked = None;
+        let w = bevy_egui::egui::Window::new(tr("build.title"))
             .open(&mut g.build_mode)
             .anchor(Align2::RIGHT_CENTER, [0., 0.])
             .show(egui_context.ctx(), |ui| {
                 ui.vertical(|ui| {
-                    Buildings.iter().for_each(|(k, v)| {
-                        ui.button(v.name.clone());
-
+                    Buildings.entries().iter().for_each(|(k, v)| {
+                        if ui.button(tr(&v.name)).clicked() {
+                            picked = ids::BuildingId::of(k);
+                        }
                     });
                     // ui.collapsing("Defense", |ui| {
                     //     ui.horizontal(|ui| {
@@ -169,6 +262,9 @@ pub fn in_game(egui_context: ResMut<EguiContext>,
                     // });
                 });
             });
+        if picked.is_some() {
+            g.building = picked;
+        }
     } // build menu
     // toolbar
     bevy_egui::egui::Window::new("toolbelt")
@@ -193,24 +289,56 @@ pub fn in_game(egui_context: ResMut<EguiContext>,
             })
         }); // toolbar
     if g.show_inventory {
-        egui::Window::new("Player Inventory")
+        // the selected colonist, unless it's gone since
+        let mut colonist = g.selected.filter(|e| inventories.get(*e).is_ok());
+        let mut selected = g.inventory_selection;
+        egui::Window::new(tr("inventory.title"))
             .open(&mut g.show_inventory)
             .show(egui_context.ctx(), |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (e, l, p, _) in inventories.iter() {
+                        let name = p.map(|p| p.name.clone()).unwrap_or_else(|| tr(&l.id));
+                        if ui.selectable_label(colonist == Some(e), name).clicked() && colonist != Some(e) {
+                            colonist = Some(e);
+                            selected = None;
+                        }
+                    }
+                });
+                ui.separator();
+                let inv = match colonist.and_then(|e| inventories.get(e).ok()) {
+                    Some((_, _, _, i)) => i,
+                    None => {
+                        ui.label(tr("inventory.nobody"));
+                        return;
+                    }
+                };
                 // inventory slots and detail view
                 ui.columns(2, |uis| {
                     uis[0].vertical(|ui| {
-                        ui.horizontal(|ui| {
-                            ui.button("[slot]");
-                            ui.button("[slot]");
-                            ui.button("[slot]");
-                            ui.button("[slot]");
-                            ui.button("[slot]");
-                        });
+                        ui.label(format!("{:.1} / {:.1}", inv.weight(), inv.capacity));
+                        for (i, st) in inv.stacks.iter().enumerate() {
+                            if ui.button(format!("{} x{}", tr(&st.item.name), st.count)).clicked() {
+                                selected = Some(i);
+                            }
+                        }
                     }); // slots
                     uis[1].group(|ui| {
-                        ui.label("lbl")
+                        match selected.and_then(|i| inv.stacks.get(i)) {
+                            Some(st) => {
+                                ui.heading(tr(&st.item.name));
+                                if let Some(d) = st.item.data() {
+                                    ui.label(tr(&d.description));
+                                }
+                                ui.label(tr_with("inventory.weight", &[("weight", &format!("{:.1}", st.weight()))]));
+                            }
+                            None => {
+                                ui.label("");
+                            }
+                        }
                     })
                 });
             });
+        g.inventory_selection = selected;
+        g.selected = colonist;
     } // inventory
 }
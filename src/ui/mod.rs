@@ -7,7 +7,10 @@ use bevy_egui::{
 };
 use crate::AppState;
 use crate::ui::menus::{main_menu, new_game};
-use crate::sim::world::entity::Living;
+use crate::sim::world::{
+    data::locale::tr,
+    entity::{Living, Profile},
+};
 use bevy::utils::HashMap;
 
 pub mod research;
@@ -34,39 +37,40 @@ impl VisibleUI {
         }
     }
 
-    pub fn draw(&mut self, e: Entity, l: &Living, ctx: &CtxRef) {
+    pub fn draw(&mut self, e: Entity, l: &Living, profile: Option<&Profile>, ctx: &CtxRef) {
         match self.name {
             UIKind::Character => {
-                egui::Window::new("Character")
+                let title = profile.map(|p| p.name.clone()).unwrap_or_else(|| tr("character.title"));
+                egui::Window::new(title)
                     .open(&mut self.close)
                     .id(Id::new(e.id()+1))
                     .show(ctx, |ui| {
                         ui.vertical(|ui| {
                             let attrs = l.attrs;
-                            ui.columns(2, |ui| {
-                                ui[0].label("Strength");
-                                ui[1].label(attrs.strength.to_string());
-                            });
-                            ui.columns(2, |ui| {
-                                ui[0].label("Constitution");
-                                ui[1].label(attrs.constitution.to_string());
-                            });
-                            ui.columns(2, |ui| {
-                                ui[0].label("Dexterity");
-                                ui[1].label(attrs.dexterity.to_string());
-                            });
-                            ui.columns(2, |ui| {
-                                ui[0].label("Agility");
-                                ui[1].label(attrs.agility.to_string());
-                            });
-                            ui.columns(2, |ui| {
-                                ui[0].label("Intelligence");
-                                ui[1].label(attrs.intelligence.to_string());
-                            });
-                            ui.columns(2, |ui| {
-                                ui[0].label("Luck");
-                                ui[1].label(attrs.luck.to_string());
-                            });
+                            let rows = [
+                                ("attr.strength", attrs.strength),
+                                ("attr.constitution", attrs.constitution),
+                                ("attr.dexterity", attrs.dexterity),
+                                ("attr.agility", attrs.agility),
+                                ("attr.intelligence", attrs.intelligence),
+                                ("attr.luck", attrs.luck),
+                            ];
+                            for (key, value) in rows.iter() {
+                                ui.columns(2, |ui| {
+                                    ui[0].label(tr(key));
+                                    ui[1].label(value.to_string());
+                                });
+                            }
+                            let p = match profile {
+                                Some(p) if !p.traits.is_empty() => p,
+                                _ => return,
+                            };
+                            ui.separator();
+                            ui.heading(tr("character.traits"));
+                            for t in p.traits.iter() {
+                                ui.label(tr(&t.name));
+                                ui.label(t.describe(p));
+                            }
                         })
                     });
             }
@@ -79,7 +83,10 @@ pub struct System;
 impl Plugin for System {
     fn build(&self, app: &mut App) {
         app.add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(menus::main_menu))
+            .add_system_set(SystemSet::on_update(AppState::DataError).with_system(menus::data_errors))
+            .add_system_set(SystemSet::on_update(AppState::Settings).with_system(menus::settings))
             .add_system_set(SystemSet::on_update(AppState::NewGame).with_system(menus::new_game))
+            .add_system_set(SystemSet::on_update(AppState::Loading).with_system(menus::loading))
             // .add_system_set(SystemSet::on_update(GameState::Loading).with_system(loading.system()))
             .add_system_set(SystemSet::on_update(AppState::InGame).with_system(menus::in_game));
     }